@@ -27,6 +27,7 @@ fn main() {
 
     println!("cargo:rustc-check-cfg=cfg(hidapi)");
     println!("cargo:rustc-check-cfg=cfg(libusb)");
+    println!("cargo:rustc-check-cfg=cfg(fuzzing)");
 
     if target.contains("linux") {
         compile_linux();
@@ -49,7 +50,7 @@ fn compile_linux() {
     // First check the features enabled for the crate.
     // Only one linux backend should be enabled at a time.
 
-    let avail_backends: [(&'static str, Box<dyn Fn()>); 5] = [
+    let avail_backends: [(&'static str, Box<dyn Fn()>); 6] = [
         (
             "LINUX_STATIC_HIDRAW",
             Box::new(|| {
@@ -106,6 +107,12 @@ fn compile_linux() {
                 // The udev crate takes care of finding its library
             }),
         ),
+        (
+            "LINUX_NATIVE_SYSFS",
+            Box::new(|| {
+                // Reads sysfs directly, no system libraries needed
+            }),
+        ),
     ];
 
     let mut backends = avail_backends
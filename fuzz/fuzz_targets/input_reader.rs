@@ -0,0 +1,18 @@
+//! Feed arbitrary report descriptors and reports to the descriptor parser.
+//!
+//! Run with `cargo fuzz run input_reader` from the repository root. The corpus
+//! is seeded with the descriptors from `tests/assets`.
+
+#![no_main]
+
+use hidapi::input::InputReader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = InputReader::from_descriptor_strict(data);
+
+    // The descriptor bytes double as reports of different lengths
+    let reader = InputReader::from_descriptor(data);
+    let _ = reader.parse(data).iter().count();
+    let _ = reader.parse(&data[data.len() / 2..]).iter().count();
+});
@@ -0,0 +1,21 @@
+//! Feed arbitrary report descriptors to the descriptor parser shared by all
+//! backends.
+//!
+//! Run with `cargo fuzz run report_descriptor` from the repository root. The
+//! corpus is seeded with the descriptors from `tests/assets`.
+
+#![no_main]
+
+use hidapi::{FuzzReportDescriptor as ReportDescriptor, ReportType};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let descriptor = ReportDescriptor::from_vec(data.to_vec());
+    let _ = descriptor.validate();
+    let _ = descriptor.items().count();
+    let _ = descriptor.usages().count();
+    let _ = descriptor.uses_numbered_reports();
+    for report_type in [ReportType::Input, ReportType::Output, ReportType::Feature] {
+        let _ = descriptor.max_report_len(report_type);
+    }
+});
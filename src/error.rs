@@ -31,6 +31,31 @@ pub enum HidError {
     OpenHidDeviceWithDeviceInfoError {
         device_info: Box<DeviceInfo>,
     },
+    /// No connected device matches the vid, pid or other criteria to open it by
+    ///
+    /// The criteria which were not used to look up the device are `None`. Errors
+    /// while opening a device which was found are reported separately, e.g. as
+    /// [`HidError::PermissionDenied`].
+    DeviceNotFound {
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: Option<String>,
+        usage_page: Option<u16>,
+        usage: Option<u16>,
+    },
+    /// A blocking read was cancelled with [`ReadCanceller::cancel`](crate::ReadCanceller::cancel)
+    Cancelled,
+    /// The operation did not finish before its timeout, e.g. in
+    /// [`HidApi::open_timeout`](crate::HidApi::open_timeout)
+    Timeout,
+    /// The report descriptor contains a truncated item at the given byte offset
+    InvalidDescriptor {
+        offset: usize,
+    },
+    /// The device exists, but the user is not allowed to open it
+    PermissionDenied {
+        path: String,
+    },
     /// An IO error or a system error that can be represented as such
     IoError {
         error: std::io::Error,
@@ -60,6 +85,34 @@ impl Display for HidError {
             HidError::OpenHidDeviceWithDeviceInfoError { device_info } => {
                 write!(f, "Can not open hid device with: {:?}", *device_info)
             }
+            HidError::DeviceNotFound {
+                vendor_id,
+                product_id,
+                serial_number,
+                usage_page,
+                usage,
+            } => {
+                write!(f, "No hid device {vendor_id:04x}:{product_id:04x}")?;
+                if let Some(serial_number) = serial_number {
+                    write!(f, " with serial number {serial_number:?}")?;
+                }
+                if let Some(usage_page) = usage_page {
+                    write!(f, " with usage page {usage_page:#06x}")?;
+                }
+                if let Some(usage) = usage {
+                    write!(f, " with usage {usage:#06x}")?;
+                }
+                write!(f, " found")
+            }
+            HidError::Cancelled => write!(f, "Read was cancelled"),
+            HidError::Timeout => write!(f, "Operation timed out"),
+            HidError::InvalidDescriptor { offset } => {
+                write!(f, "Malformed report descriptor item at offset {offset}")
+            }
+            HidError::PermissionDenied { path } => write!(
+                f,
+                "Permission denied opening {path}, check that the udev rules for the device are set up"
+            ),
             HidError::IoError { error } => {
                 write!(f, "{error}")
             }
@@ -67,6 +120,28 @@ impl Display for HidError {
     }
 }
 
+impl HidError {
+    /// The error for a device looked up by vid, pid and optionally serial number
+    pub(crate) fn device_not_found(vendor_id: u16, product_id: u16, sn: Option<&str>) -> Self {
+        HidError::DeviceNotFound {
+            vendor_id,
+            product_id,
+            serial_number: sn.map(str::to_string),
+            usage_page: None,
+            usage: None,
+        }
+    }
+
+    /// Check if the error was caused by missing permissions to access the device.
+    pub fn is_permission_denied(&self) -> bool {
+        match self {
+            HidError::PermissionDenied { .. } => true,
+            HidError::IoError { error } => error.kind() == std::io::ErrorKind::PermissionDenied,
+            _ => false,
+        }
+    }
+}
+
 impl Error for HidError {}
 
 impl From<std::io::Error> for HidError {
@@ -75,7 +150,10 @@ impl From<std::io::Error> for HidError {
     }
 }
 
-#[cfg(all(feature = "linux-native", target_os = "linux"))]
+#[cfg(all(
+    any(feature = "linux-native", feature = "linux-native-sysfs"),
+    target_os = "linux"
+))]
 impl From<nix::errno::Errno> for HidError {
     fn from(e: nix::errno::Errno) -> Self {
         Self::IoError { error: e.into() }
@@ -115,6 +115,52 @@ pub mod macos {
         pub fn hid_darwin_get_open_exclusive() -> c_int;
         pub fn hid_darwin_is_device_open_exclusive(device: *mut HidDevice) -> c_int;
     }
+
+    // The parts of IOKit and CoreFoundation needed to read IORegistry properties
+    // and to open devices without the C library
+    pub type CFTypeRef = *const c_void;
+    pub type CFStringRef = *const c_void;
+    pub type CFIndex = isize;
+
+    pub const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    pub const K_CF_NUMBER_SINT32_TYPE: CFIndex = 3;
+
+    extern "C" {
+        pub fn IORegistryEntryIDMatching(entry_id: u64) -> *mut c_void;
+        pub fn IOServiceGetMatchingService(main_port: u32, matching: *const c_void) -> u32;
+        pub fn IORegistryEntryCreateCFProperty(
+            entry: u32,
+            key: CFStringRef,
+            allocator: *const c_void,
+            options: u32,
+        ) -> CFTypeRef;
+        pub fn IOObjectRelease(object: u32) -> c_int;
+
+        pub fn IOHIDDeviceCreate(allocator: *const c_void, service: u32) -> CFTypeRef;
+        pub fn IOHIDDeviceOpen(device: CFTypeRef, options: u32) -> c_int;
+        pub fn IOHIDDeviceClose(device: CFTypeRef, options: u32) -> c_int;
+
+        pub fn CFStringCreateWithBytes(
+            allocator: *const c_void,
+            bytes: *const u8,
+            num_bytes: CFIndex,
+            encoding: u32,
+            is_external_representation: u8,
+        ) -> CFStringRef;
+        pub fn CFStringGetTypeID() -> usize;
+        pub fn CFStringGetLength(string: CFStringRef) -> CFIndex;
+        pub fn CFStringGetMaximumSizeForEncoding(length: CFIndex, encoding: u32) -> CFIndex;
+        pub fn CFStringGetCString(
+            string: CFStringRef,
+            buffer: *mut c_char,
+            buffer_size: CFIndex,
+            encoding: u32,
+        ) -> u8;
+        pub fn CFNumberGetTypeID() -> usize;
+        pub fn CFNumberGetValue(number: CFTypeRef, number_type: CFIndex, value: *mut c_void) -> u8;
+        pub fn CFGetTypeID(cf: CFTypeRef) -> usize;
+        pub fn CFRelease(cf: CFTypeRef);
+    }
 }
 
 // For documentation look at the corresponding C header file hidapi_winapi.h
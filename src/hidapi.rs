@@ -1,13 +1,18 @@
 //! The implementation which uses the C library to perform operations
 
 use std::{
+    cell::Cell,
     ffi::CStr,
     fmt::{self, Debug},
+    time::Instant,
 };
 
 use libc::{c_int, size_t, wchar_t};
 
-use crate::{ffi, DeviceInfo, HidDeviceBackendBase, HidError, HidResult, WcharString};
+use crate::{
+    check_deadline, ffi, Capabilities, DeviceInfo, HidDeviceBackendBase, HidError, HidResult,
+    WcharString,
+};
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -19,6 +24,25 @@ const STRING_BUF_LEN: usize = 128;
 pub struct HidApiBackend;
 
 impl HidApiBackend {
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        supports_feature_reports: true,
+        supports_get_input_report: true,
+        supports_set_input_report: false,
+        supports_report_descriptor: true,
+        supports_read_canceller: false,
+        supports_pending_report_check: false,
+        supports_indexed_strings: true,
+        supports_hotplug: false,
+        supports_control_transfer: false,
+    };
+
+    /// The hidraw kernel buffer, or the HidD_SetNumInputBuffers() call on Windows.
+    /// The libusb and macOS backends queue the reports themselves.
+    #[cfg(all(any(target_os = "windows", target_os = "linux"), not(libusb)))]
+    pub const INPUT_QUEUE_SIZE: usize = 64;
+    #[cfg(not(all(any(target_os = "windows", target_os = "linux"), not(libusb))))]
+    pub const INPUT_QUEUE_SIZE: usize = 30;
+
     pub fn get_hid_device_info_vector(vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
         let mut device_vector = Vec::with_capacity(8);
 
@@ -43,10 +67,7 @@ impl HidApiBackend {
         let device = unsafe { ffi::hid_open(vid, pid, std::ptr::null()) };
 
         if device.is_null() {
-            match Self::check_error() {
-                Ok(err) => Err(err),
-                Err(e) => Err(e),
-            }
+            Err(Self::open_error(vid, pid, None))
         } else {
             Ok(HidDevice::from_raw(device))
         }
@@ -57,28 +78,80 @@ impl HidApiBackend {
         chars.push(0 as wchar_t);
         let device = unsafe { ffi::hid_open(vid, pid, chars.as_ptr()) };
         if device.is_null() {
-            match Self::check_error() {
-                Ok(err) => Err(err),
-                Err(e) => Err(e),
-            }
+            Err(Self::open_error(vid, pid, Some(sn)))
         } else {
             Ok(HidDevice::from_raw(device))
         }
     }
 
+    pub fn open_deadline(
+        vid: u16,
+        pid: u16,
+        sn: Option<&str>,
+        deadline: Option<Instant>,
+    ) -> HidResult<HidDevice> {
+        if deadline.is_none() {
+            return match sn {
+                Some(sn) => Self::open_serial(vid, pid, sn),
+                None => Self::open(vid, pid),
+            };
+        }
+
+        // hid_open() enumerates and opens in one call, which can not be
+        // interrupted, so split it up to check the deadline in between
+        let device = Self::get_hid_device_info_vector(vid, pid)?
+            .into_iter()
+            .find(|info| {
+                (info.vendor_id, info.product_id) == (vid, pid)
+                    && (sn.is_none() || sn == info.serial_number())
+            })
+            .ok_or_else(|| HidError::device_not_found(vid, pid, sn))?;
+        check_deadline(deadline)?;
+        Self::open_path(device.path())
+    }
+
     pub fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
         let device = unsafe { ffi::hid_open_path(device_path.as_ptr()) };
 
         if device.is_null() {
-            match Self::check_error() {
-                Ok(err) => Err(err),
-                Err(e) => Err(e),
-            }
+            let error = match Self::check_error() {
+                Ok(err) => err,
+                Err(e) => e,
+            };
+            #[cfg(target_os = "macos")]
+            let error = crate::macos::open_path_error(error, device_path);
+            Err(error)
         } else {
             Ok(HidDevice::from_raw(device))
         }
     }
 
+    /// The error for a failed `hid_open()`
+    ///
+    /// The C library reports a missing device only as an error message, which
+    /// differs between its backends, so look for the device again. On macOS the
+    /// message contains the `IOReturn` code, which tells missing permissions apart.
+    fn open_error(vid: u16, pid: u16, sn: Option<&str>) -> HidError {
+        let error = match Self::check_error() {
+            Ok(err) => err,
+            Err(e) => e,
+        };
+
+        let Ok(devices) = Self::get_hid_device_info_vector(vid, pid) else {
+            return error;
+        };
+        match devices.iter().find(|info| {
+            (info.vendor_id, info.product_id) == (vid, pid)
+                && (sn.is_none() || sn == info.serial_number())
+        }) {
+            None => HidError::device_not_found(vid, pid, sn),
+            #[cfg(target_os = "macos")]
+            Some(info) => crate::macos::open_path_error(error, info.path()),
+            #[cfg(not(target_os = "macos"))]
+            Some(_) => error,
+        }
+    }
+
     pub fn check_error() -> HidResult<HidError> {
         Ok(HidError::HidApiError {
             message: unsafe {
@@ -97,33 +170,25 @@ unsafe fn wchar_to_string(wstr: *const wchar_t) -> WcharString {
         return WcharString::None;
     }
 
-    let mut char_vector: Vec<char> = Vec::with_capacity(8);
-    let mut raw_vector: Vec<wchar_t> = Vec::with_capacity(8);
-    let mut index: isize = 0;
-    let mut invalid_char = false;
-
-    let o = |i| *wstr.offset(i);
-
-    while o(index) != 0 {
-        use std::char;
-
-        raw_vector.push(*wstr.offset(index));
-
-        if !invalid_char {
-            if let Some(c) = char::from_u32(o(index) as u32) {
-                char_vector.push(c);
-            } else {
-                invalid_char = true;
-            }
-        }
-
-        index += 1;
+    let mut len = 0;
+    while *wstr.add(len) != 0 {
+        len += 1;
     }
-
-    if !invalid_char {
-        WcharString::String(char_vector.into_iter().collect())
-    } else {
-        WcharString::Raw(raw_vector)
+    let raw = std::slice::from_raw_parts(wstr, len);
+
+    // wchar_t is UTF-16 on Windows, so characters outside of the BMP are made
+    // up of two surrogates
+    #[cfg(target_os = "windows")]
+    let decoded = String::from_utf16(raw).ok();
+    #[cfg(not(target_os = "windows"))]
+    let decoded = raw
+        .iter()
+        .map(|&c| char::from_u32(c as u32))
+        .collect::<Option<String>>();
+
+    match decoded {
+        Some(s) => WcharString::String(s),
+        None => WcharString::Raw(raw.to_vec()),
     }
 }
 
@@ -137,22 +202,27 @@ pub unsafe fn conv_hid_device_info(src: *mut ffi::HidDeviceInfo) -> HidResult<De
         release_number: (*src).release_number,
         manufacturer_string: wchar_to_string((*src).manufacturer_string),
         product_string: wchar_to_string((*src).product_string),
+        raw_name: WcharString::None,
         usage_page: (*src).usage_page,
         usage: (*src).usage,
         interface_number: (*src).interface_number,
         bus_type: (*src).bus_type,
+        country_code: None,
     })
 }
 
 /// Object for accessing HID device
 pub struct HidDevice {
     _hid_device: *mut ffi::HidDevice,
+    blocking: Cell<bool>,
 }
 
 impl HidDevice {
     pub fn from_raw(device: *mut ffi::HidDevice) -> Self {
         Self {
             _hid_device: device,
+            // hidapi opens all devices in blocking mode
+            blocking: Cell::new(true),
         }
     }
 }
@@ -203,7 +273,17 @@ impl HidDeviceBackendBase for HidDevice {
             return Err(HidError::InvalidZeroSizeData);
         }
         let res = unsafe { ffi::hid_write(self._hid_device, data.as_ptr(), data.len() as size_t) };
-        self.check_size(res)
+        let res = self.check_size(res)?;
+        // Some backends (Windows) pad the report to the full output report
+        // length, so only a write shorter than the data is an error.
+        if res < data.len() {
+            Err(HidError::IncompleteSendError {
+                sent: res,
+                all: data.len(),
+            })
+        } else {
+            Ok(res)
+        }
     }
 
     fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
@@ -291,10 +371,15 @@ impl HidDeviceBackendBase for HidDevice {
                 },
             })
         } else {
+            self.blocking.set(blocking);
             Ok(())
         }
     }
 
+    fn is_blocking(&self) -> bool {
+        self.blocking.get()
+    }
+
     fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
         let mut buf = [0 as wchar_t; STRING_BUF_LEN];
         let res = unsafe {
@@ -1,6 +1,7 @@
 //! The extra beahviour for macOS
 
 use super::HidDevice;
+use crate::macos::{cf_i32, cf_string, registry_property};
 use crate::{ffi, HidDeviceBackendBase, HidDeviceBackendMacos, HidResult};
 
 impl HidDeviceBackendMacos for HidDevice {
@@ -33,4 +34,12 @@ impl HidDeviceBackendMacos for HidDevice {
             Ok(res == 1)
         }
     }
+
+    fn get_custom_property(&self, key: &str) -> HidResult<Option<String>> {
+        registry_property(self.get_device_info()?.path(), key, cf_string)
+    }
+
+    fn get_custom_property_i32(&self, key: &str) -> HidResult<Option<i32>> {
+        registry_property(self.get_device_info()?.path(), key, cf_i32)
+    }
 }
@@ -0,0 +1,461 @@
+//! Decoding of Input reports into values for each usage, based on the report
+//! descriptor of the device
+//!
+//! ```no_run
+//! use hidapi::input::{InputReader, GENERIC_DESKTOP, X};
+//!
+//! let api = hidapi::HidApi::new().unwrap();
+//! let device = api.open(0x046d, 0xc24f).unwrap();
+//! let reader = InputReader::new(&device).unwrap();
+//!
+//! loop {
+//!     let report = reader.read(&device).unwrap();
+//!     println!("X: {:?}", report.get(GENERIC_DESKTOP, X));
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::report::{read_bits, sign_extend};
+use crate::report_descriptor::ReportDescriptor;
+use crate::{HidDevice, HidResult};
+
+/// Generic Desktop usage page
+pub const GENERIC_DESKTOP: u16 = 0x01;
+/// Button usage page, the usage is the number of the button starting at 1
+pub const BUTTON: u16 = 0x09;
+
+// Usages of the Generic Desktop page
+pub const X: u16 = 0x30;
+pub const Y: u16 = 0x31;
+pub const Z: u16 = 0x32;
+pub const RX: u16 = 0x33;
+pub const RY: u16 = 0x34;
+pub const RZ: u16 = 0x35;
+pub const SLIDER: u16 = 0x36;
+pub const DIAL: u16 = 0x37;
+pub const WHEEL: u16 = 0x38;
+pub const HAT_SWITCH: u16 = 0x39;
+
+/// Reports can not be longer than this, which also protects against
+/// descriptors with absurd report counts
+const MAX_REPORT_BITS: usize = 16384 * 8;
+
+/// Decodes the Input reports of a device, see the [module documentation](self)
+pub struct InputReader {
+    fields: Vec<Field>,
+    uses_report_ids: bool,
+    report_len: usize,
+}
+
+struct Field {
+    report_id: u8,
+    bit_offset: usize,
+    bit_size: usize,
+    logical_min: i32,
+    logical_max: i32,
+    kind: FieldKind,
+}
+
+enum FieldKind {
+    /// The field contains the value of a single usage
+    Variable { usage_page: u16, usage: u16 },
+    /// The field contains an index into the usages, e.g. of the pressed key
+    Array { usages: Arc<[(u16, u16)]> },
+}
+
+#[derive(Clone, Default)]
+struct GlobalState {
+    usage_page: u16,
+    logical_min: i32,
+    logical_max_signed: i32,
+    logical_max_unsigned: u32,
+    report_size: usize,
+    report_count: usize,
+    report_id: u8,
+}
+
+impl GlobalState {
+    fn logical_range(&self) -> (i32, i32) {
+        // Most devices with a non-negative minimum encode the maximum as
+        // unsigned, e.g. 0xff in a single byte for 255
+        let logical_max = if self.logical_min < 0 {
+            self.logical_max_signed
+        } else {
+            self.logical_max_unsigned.min(i32::MAX as u32) as i32
+        };
+        (self.logical_min, logical_max)
+    }
+}
+
+/// Usages with an explicit usage page stay unresolved until the main item
+#[derive(Default)]
+struct LocalState {
+    usages: Vec<(Option<u16>, u16)>,
+    usage_min: Option<(Option<u16>, u16)>,
+    usage_max: Option<u16>,
+}
+
+impl LocalState {
+    fn usages(&self, usage_page: u16) -> Vec<(u16, u16)> {
+        let mut usages: Vec<_> = self
+            .usages
+            .iter()
+            .map(|&(page, usage)| (page.unwrap_or(usage_page), usage))
+            .collect();
+
+        if let (Some((page, min)), Some(max)) = (self.usage_min, self.usage_max) {
+            let page = page.unwrap_or(usage_page);
+            usages.extend((min..=max).map(|usage| (page, usage)));
+        }
+
+        usages
+    }
+}
+
+/// Split the data of a Usage item, which includes the usage page if it is 4 bytes long
+fn extended_usage(item: &crate::report_descriptor::Item) -> (Option<u16>, u16) {
+    if item.size == 4 {
+        (Some((item.data >> 16) as u16), item.data as u16)
+    } else {
+        (None, item.data as u16)
+    }
+}
+
+impl InputReader {
+    /// Create a reader for the device from its report descriptor
+    pub fn new(device: &HidDevice) -> HidResult<Self> {
+        Ok(Self::from_descriptor(&device.get_report_descriptor_vec()?))
+    }
+
+    /// Create a reader from a raw report descriptor
+    ///
+    /// Parsing stops at the first malformed item, the fields up to it are
+    /// still decoded.
+    pub fn from_descriptor(descriptor: &[u8]) -> Self {
+        Self::from_report_descriptor(&ReportDescriptor::from_vec(descriptor.to_vec()))
+    }
+
+    /// Create a reader from a raw report descriptor, failing with
+    /// [`HidError::InvalidDescriptor`](crate::HidError::InvalidDescriptor) if it
+    /// contains a malformed item
+    pub fn from_descriptor_strict(descriptor: &[u8]) -> HidResult<Self> {
+        let descriptor = ReportDescriptor::from_vec(descriptor.to_vec());
+        descriptor.validate()?;
+        Ok(Self::from_report_descriptor(&descriptor))
+    }
+
+    fn from_report_descriptor(descriptor: &ReportDescriptor) -> Self {
+        let mut fields = Vec::new();
+        let mut global = GlobalState::default();
+        let mut global_stack = Vec::new();
+        let mut local = LocalState::default();
+        let mut offsets: HashMap<u8, usize> = HashMap::new();
+
+        for item in descriptor.items() {
+            match item.prefix {
+                // Input 6.2.2.4 (Main)
+                0x80 => {
+                    let offset = offsets.entry(global.report_id).or_insert(0);
+                    let bits = match global.report_size.checked_mul(global.report_count) {
+                        Some(bits) if *offset + bits <= MAX_REPORT_BITS => bits,
+                        _ => break,
+                    };
+
+                    let constant = item.data & 0x01 != 0;
+                    let variable = item.data & 0x02 != 0;
+                    let usages: Arc<[_]> = local.usages(global.usage_page).into();
+                    let (logical_min, logical_max) = global.logical_range();
+
+                    if !constant && !usages.is_empty() && (1..=32).contains(&global.report_size) {
+                        for i in 0..global.report_count {
+                            let kind = if variable {
+                                // The last usage applies to all remaining fields
+                                let (usage_page, usage) = usages[i.min(usages.len() - 1)];
+                                FieldKind::Variable { usage_page, usage }
+                            } else {
+                                FieldKind::Array {
+                                    usages: usages.clone(),
+                                }
+                            };
+
+                            fields.push(Field {
+                                report_id: global.report_id,
+                                bit_offset: *offset + i * global.report_size,
+                                bit_size: global.report_size,
+                                logical_min,
+                                logical_max,
+                                kind,
+                            });
+                        }
+                    }
+
+                    *offset += bits;
+                    local = LocalState::default();
+                }
+                // Output, Feature, Collection, End Collection 6.2.2.4 (Main)
+                0x90 | 0xb0 | 0xa0 | 0xc0 => local = LocalState::default(),
+                // Usage Page 6.2.2.7 (Global)
+                0x04 => global.usage_page = item.data as u16,
+                // Logical Minimum 6.2.2.7 (Global)
+                0x14 => global.logical_min = item.signed_data(),
+                // Logical Maximum 6.2.2.7 (Global)
+                0x24 => {
+                    global.logical_max_signed = item.signed_data();
+                    global.logical_max_unsigned = item.data;
+                }
+                // Report Size 6.2.2.7 (Global)
+                0x74 => global.report_size = item.data as usize,
+                // Report ID 6.2.2.7 (Global)
+                0x84 => global.report_id = item.data as u8,
+                // Report Count 6.2.2.7 (Global)
+                0x94 => global.report_count = item.data as usize,
+                // Push 6.2.2.7 (Global)
+                0xa4 => global_stack.push(global.clone()),
+                // Pop 6.2.2.7 (Global)
+                0xb4 => global = global_stack.pop().unwrap_or_default(),
+                // Usage 6.2.2.8 (Local)
+                0x08 => local.usages.push(extended_usage(&item)),
+                // Usage Minimum 6.2.2.8 (Local)
+                0x18 => local.usage_min = Some(extended_usage(&item)),
+                // Usage Maximum 6.2.2.8 (Local)
+                0x28 => local.usage_max = Some(item.data as u16),
+                _ => {}
+            }
+        }
+
+        let uses_report_ids = offsets.keys().any(|&id| id != 0);
+        let report_len = offsets.values().max().map_or(0, |bits| bits.div_ceil(8))
+            + usize::from(uses_report_ids);
+
+        Self {
+            fields,
+            uses_report_ids,
+            report_len,
+        }
+    }
+
+    /// Read the next Input report from the device and decode it
+    ///
+    /// This blocks like [`HidDevice::read`]. If no report was available in
+    /// non-blocking mode, the returned report is empty.
+    pub fn read(&self, device: &HidDevice) -> HidResult<InputReport> {
+        let mut buf = vec![0u8; self.report_len.max(1)];
+        let len = device.read(&mut buf)?;
+        Ok(self.parse(&buf[..len]))
+    }
+
+    /// Decode an Input report, as returned by [`HidDevice::read`]
+    ///
+    /// If the device uses numbered reports, the first byte must be the report ID.
+    pub fn parse(&self, report: &[u8]) -> InputReport {
+        let (report_id, base) = match (self.uses_report_ids, report.first()) {
+            (true, Some(&id)) => (id, 8),
+            _ => (0, 0),
+        };
+        let mut values = Vec::new();
+
+        for field in self.fields.iter().filter(|f| f.report_id == report_id) {
+            let raw = match read_bits(report, base + field.bit_offset, field.bit_size) {
+                Some(raw) => raw,
+                None => continue,
+            };
+
+            match &field.kind {
+                FieldKind::Variable { usage_page, usage } => {
+                    let raw = if field.logical_min < 0 {
+                        sign_extend(raw, field.bit_size)
+                    } else {
+                        raw as i32
+                    };
+                    values.push(Value {
+                        usage_page: *usage_page,
+                        usage: *usage,
+                        raw,
+                        logical_min: field.logical_min,
+                        logical_max: field.logical_max,
+                    });
+                }
+                FieldKind::Array { usages } => {
+                    let index = i64::from(raw) - i64::from(field.logical_min);
+                    let selected = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| usages.get(index));
+
+                    // Usage 0 is reserved and means that no usage is selected
+                    if let Some(&(usage_page, usage)) = selected.filter(|u| u.1 != 0) {
+                        values.push(Value {
+                            usage_page,
+                            usage,
+                            raw: 1,
+                            logical_min: 0,
+                            logical_max: 1,
+                        });
+                    }
+                }
+            }
+        }
+
+        InputReport { report_id, values }
+    }
+}
+
+/// One decoded field of an Input report, together with its logical range
+struct Value {
+    usage_page: u16,
+    usage: u16,
+    raw: i32,
+    logical_min: i32,
+    logical_max: i32,
+}
+
+/// A decoded Input report, see [`InputReader`]
+pub struct InputReport {
+    report_id: u8,
+    values: Vec<Value>,
+}
+
+impl InputReport {
+    /// The report ID, or 0 if the device does not use numbered reports
+    pub fn report_id(&self) -> u8 {
+        self.report_id
+    }
+
+    /// Get the value of the usage, scaled from its logical range to `0.0..=1.0`
+    ///
+    /// For buttons and selected array usages (e.g. pressed keys) this is 1.0.
+    /// Returns `None` if the report does not contain the usage.
+    pub fn get(&self, usage_page: u16, usage: u16) -> Option<f32> {
+        self.value(usage_page, usage).map(|value| {
+            if value.logical_max <= value.logical_min {
+                return value.raw as f32;
+            }
+
+            let range = f64::from(value.logical_max) - f64::from(value.logical_min);
+            ((f64::from(value.raw) - f64::from(value.logical_min)) / range) as f32
+        })
+    }
+
+    /// Get the value of the usage as it was sent by the device
+    pub fn get_raw(&self, usage_page: u16, usage: u16) -> Option<i32> {
+        self.value(usage_page, usage).map(|value| value.raw)
+    }
+
+    /// Iterate over the `(usage_page, usage, raw value)` of all values in the report
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u16, i32)> + '_ {
+        self.values
+            .iter()
+            .map(|value| (value.usage_page, value.usage, value.raw))
+    }
+
+    fn value(&self, usage_page: u16, usage: u16) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|value| value.usage_page == usage_page && value.usage == usage)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[rustfmt::skip]
+    const GAMEPAD: &[u8] = &[
+        0x05, 0x01,       // Usage Page (Generic Desktop)
+        0x09, 0x05,       // Usage (Game Pad)
+        0xa1, 0x01,       // Collection (Application)
+        0x85, 0x01,       //   Report ID (1)
+        0x05, 0x09,       //   Usage Page (Button)
+        0x19, 0x01,       //   Usage Minimum (1)
+        0x29, 0x04,       //   Usage Maximum (4)
+        0x15, 0x00,       //   Logical Minimum (0)
+        0x25, 0x01,       //   Logical Maximum (1)
+        0x75, 0x01,       //   Report Size (1)
+        0x95, 0x04,       //   Report Count (4)
+        0x81, 0x02,       //   Input (Data, Variable, Absolute)
+        0x81, 0x03,       //   Input (Constant), padding
+        0x05, 0x01,       //   Usage Page (Generic Desktop)
+        0x09, 0x30,       //   Usage (X)
+        0x09, 0x31,       //   Usage (Y)
+        0x15, 0x81,       //   Logical Minimum (-127)
+        0x25, 0x7f,       //   Logical Maximum (127)
+        0x75, 0x08,       //   Report Size (8)
+        0x95, 0x02,       //   Report Count (2)
+        0x81, 0x02,       //   Input (Data, Variable, Absolute)
+        0x09, 0x32,       //   Usage (Z)
+        0x15, 0x00,       //   Logical Minimum (0)
+        0x27, 0xff, 0xff, 0x00, 0x00, // Logical Maximum (65535)
+        0x75, 0x10,       //   Report Size (16)
+        0x95, 0x01,       //   Report Count (1)
+        0x81, 0x02,       //   Input (Data, Variable, Absolute)
+        0xc0,             // End Collection
+    ];
+
+    #[test]
+    fn test_strict_and_random_descriptors() {
+        assert!(InputReader::from_descriptor_strict(GAMEPAD).is_ok());
+        // Cut off after the last Input prefix
+        let truncated = &GAMEPAD[..GAMEPAD.len() - 2];
+        assert!(InputReader::from_descriptor_strict(truncated).is_err());
+
+        // None of this may panic
+        for seed in 0..2000 {
+            let data = crate::report_descriptor::test::random_bytes(seed, (seed % 300) as usize);
+            let reader = InputReader::from_descriptor(&data);
+            let report = reader.parse(&data);
+            let _ = report.iter().count();
+        }
+    }
+
+    #[test]
+    fn test_reserved_item() {
+        let reader =
+            InputReader::from_descriptor_strict(crate::report_descriptor::test::RESERVED_ITEM)
+                .unwrap();
+        assert_eq!(2, reader.report_len);
+    }
+
+    #[test]
+    fn test_gamepad() {
+        let reader = InputReader::from_descriptor(GAMEPAD);
+        assert_eq!(6, reader.report_len);
+
+        let report = reader.parse(&[0x01, 0b0000_0101, 0x7f, 0x81, 0x00, 0x80]);
+        assert_eq!(1, report.report_id());
+        assert_eq!(Some(1.0), report.get(BUTTON, 1));
+        assert_eq!(Some(0.0), report.get(BUTTON, 2));
+        assert_eq!(Some(1.0), report.get(BUTTON, 3));
+        assert_eq!(Some(0.0), report.get(BUTTON, 4));
+        assert_eq!(None, report.get(BUTTON, 5));
+        assert_eq!(Some(127), report.get_raw(GENERIC_DESKTOP, X));
+        assert_eq!(Some(1.0), report.get(GENERIC_DESKTOP, X));
+        assert_eq!(Some(-127), report.get_raw(GENERIC_DESKTOP, Y));
+        assert_eq!(Some(0.0), report.get(GENERIC_DESKTOP, Y));
+        assert_eq!(Some(0x8000), report.get_raw(GENERIC_DESKTOP, Z));
+
+        // Reports with other IDs do not contain any of the fields
+        let report = reader.parse(&[0x02, 0xff, 0x7f, 0x81, 0x00, 0x80]);
+        assert_eq!(0, report.iter().count());
+    }
+
+    #[test]
+    fn test_array() {
+        #[rustfmt::skip]
+        let descriptor = [
+            0x05, 0x07,       // Usage Page (Keyboard)
+            0x19, 0x00,       // Usage Minimum (0)
+            0x29, 0x65,       // Usage Maximum (101)
+            0x15, 0x00,       // Logical Minimum (0)
+            0x25, 0x65,       // Logical Maximum (101)
+            0x75, 0x08,       // Report Size (8)
+            0x95, 0x02,       // Report Count (2)
+            0x81, 0x00,       // Input (Data, Array, Absolute)
+        ];
+        let reader = InputReader::from_descriptor(&descriptor);
+
+        let report = reader.parse(&[0x04, 0x00]);
+        assert_eq!(vec![(0x07, 0x04, 1)], report.iter().collect::<Vec<_>>());
+        assert_eq!(Some(1.0), report.get(0x07, 0x04));
+    }
+}
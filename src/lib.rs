@@ -44,10 +44,16 @@
 //! - `linux-shared-libusb`: uses dynamically linked `libusb` backend on Linux
 //! - `linux-shared-hidraw`: uses dynamically linked `hidraw` backend on Linux
 //! - `linux-native`: talks to hidraw directly without using the `hidapi` C library
+//! - `linux-native-sysfs`: like `linux-native`, but discovers devices through sysfs instead
+//!   of `libudev`, which allows fully static builds (e.g. with musl)
 //! - `illumos-static-libusb`: uses statically linked `libusb` backend on Illumos (default)
 //! - `illumos-shared-libusb`: uses statically linked `hidraw` backend on Illumos
 //! - `macos-shared-device`: enables shared access to HID devices on MacOS
 //! - `windows-native`: talks to hid.dll directly without using the `hidapi` C library
+//! - `serde`: implements `Serialize` and `Deserialize` for [`DeviceInfo`] and [`BusType`]
+//! - `mock`: adds a virtual backend for testing without hardware, see [`HidApi::new_mocked`]
+//! - `input`: adds the [`input`] module, which decodes Input reports based on the report descriptor
+//! - `linux-raw-ioctl`: adds [`HidDevice::ioctl_raw`] to issue arbitrary hidraw ioctls with the `linux-native` backends
 //!
 //! ## Linux backends
 //!
@@ -63,22 +69,40 @@
 
 mod error;
 mod ffi;
+#[cfg(feature = "input")]
+pub mod input;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod report;
+mod report_descriptor;
 
 use cfg_if::cfg_if;
 use libc::wchar_t;
+use std::cell::{Cell, Ref, RefCell};
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::fmt::Debug;
+use std::sync::Arc;
 use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub use error::HidError;
+use report_descriptor::ReportDescriptor;
+
+// Set by cargo-fuzz, so the fuzz targets can reach the descriptor parser
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub use report_descriptor::ReportDescriptor as FuzzReportDescriptor;
 
 cfg_if! {
-    if #[cfg(all(feature = "linux-native", target_os = "linux"))] {
+    if #[cfg(all(any(feature = "linux-native", feature = "linux-native-sysfs"), target_os = "linux"))] {
         //#[cfg_attr(docsrs, doc(cfg(all(feature = "linux-native", target_os = "linux"))))]
         mod linux_native;
         use linux_native::HidApiBackend;
+        #[cfg(feature = "linux-raw-ioctl")]
+        pub use linux_native::hidraw_request_code;
     } else if #[cfg(all(feature = "windows-native", target_os = "windows"))] {
         //#[cfg_attr(docsrs, doc(cfg(all(feature = "windows-native", target_os = "windows"))))]
         mod windows_native;
@@ -114,6 +138,12 @@ cfg_if! {
 
             /// Check if the device was opened in exclusive mode.
             fn is_open_exclusive(&self) -> HidResult<bool>;
+
+            /// Read a string property of the device from the IORegistry
+            fn get_custom_property(&self, key: &str) -> HidResult<Option<String>>;
+
+            /// Read a numeric property of the device from the IORegistry
+            fn get_custom_property_i32(&self, key: &str) -> HidResult<Option<i32>>;
         }
         trait HidDeviceBackend: HidDeviceBackendBase + HidDeviceBackendMacos + Send {}
         impl<T> HidDeviceBackend for T where T: HidDeviceBackendBase + HidDeviceBackendMacos + Send {}
@@ -181,6 +211,8 @@ fn lazy_init(do_enumerate: bool) -> HidResult<()> {
 /// Each instance has its own device list cache.
 pub struct HidApi {
     device_list: Vec<DeviceInfo>,
+    #[cfg(feature = "mock")]
+    mock_devices: Option<Vec<mock::MockDevice>>,
 }
 
 impl HidApi {
@@ -197,6 +229,8 @@ impl HidApi {
 
         let mut api = HidApi {
             device_list: Vec::with_capacity(8),
+            #[cfg(feature = "mock")]
+            mock_devices: None,
         };
         api.add_devices(0, 0)?;
         Ok(api)
@@ -206,6 +240,13 @@ impl HidApi {
     ///
     /// This is needed on Android, where access to USB device enumeration is limited.
     ///
+    /// It is also useful for applications which should not touch unrelated devices,
+    /// e.g. because inspecting them triggers permission prompts on macOS. Together
+    /// with [`HidApi::add_devices`] for each known vid and pid, only matching devices
+    /// are requested from the operating system, while `new()` and `refresh_devices()`
+    /// enumerate all of them. Opening by vid and pid with [`HidApi::open`] is limited
+    /// to matching devices as well.
+    ///
     /// # Panics
     ///
     /// Panics if hidapi is already initialized in "do enumerate" mode
@@ -215,18 +256,58 @@ impl HidApi {
 
         Ok(HidApi {
             device_list: Vec::new(),
+            #[cfg(feature = "mock")]
+            mock_devices: None,
         })
     }
 
+    /// Create a hidapi context which only knows the given fake devices, see
+    /// the [`mock`] module.
+    ///
+    /// The hidapi C library is not initialized for this.
+    #[cfg(feature = "mock")]
+    pub fn new_mocked(mut devices: Vec<mock::MockDevice>) -> Self {
+        for (i, device) in devices.iter_mut().enumerate() {
+            device.info.path = CString::new(format!("mock:{i}")).unwrap();
+        }
+
+        HidApi {
+            device_list: devices.iter().map(|device| device.info.clone()).collect(),
+            mock_devices: Some(devices),
+        }
+    }
+
     /// Refresh devices list and information about them (to access them use
     /// `device_list()` method)
     /// Identical to `reset_devices()` followed by `add_devices(0, 0)`.
+    ///
+    /// The device list cache is updated in place, so devices plugged in after the
+    /// `HidApi` was created show up in `device_list()` without creating a new
+    /// instance. This invalidates all `&DeviceInfo` references previously returned
+    /// by `device_list()`; clone the entries you want to keep around.
     pub fn refresh_devices(&mut self) -> HidResult<()> {
         self.reset_devices()?;
         self.add_devices(0, 0)?;
         Ok(())
     }
 
+    /// Refresh the device list, and return the devices which were added and removed
+    /// since the previous one.
+    ///
+    /// Devices are compared by path, serial number and usage, so a device which is
+    /// reconnected under a different path is reported as both removed and added.
+    /// This is a simple alternative to hotplug notifications for applications
+    /// which poll for devices anyway.
+    pub fn enumerate_delta(&mut self) -> HidResult<(Vec<DeviceInfo>, Vec<DeviceInfo>)> {
+        let previous = std::mem::take(&mut self.device_list);
+        if let Err(e) = self.add_devices(0, 0) {
+            self.device_list = previous;
+            return Err(e);
+        }
+
+        Ok(device_list_delta(&previous, &self.device_list))
+    }
+
     /// Reset devices list. Intended to be used with the `add_devices` method.
     pub fn reset_devices(&mut self) -> HidResult<()> {
         self.device_list.clear();
@@ -236,42 +317,399 @@ impl HidApi {
     /// Indexes devices that match the given VID and PID filters.
     /// 0 indicates no filter.
     pub fn add_devices(&mut self, vid: u16, pid: u16) -> HidResult<()> {
-        self.device_list
-            .append(&mut HidApiBackend::get_hid_device_info_vector(vid, pid)?);
+        let mut devices = self.enumerate(vid, pid)?;
+        self.device_list.append(&mut devices);
         Ok(())
     }
 
+    /// Enumerates the devices that match the given VID and PID filters, without
+    /// touching the device list cache. 0 indicates no filter.
+    ///
+    /// Unlike [`HidApi::refresh_devices`] this only needs `&self`, so threads sharing
+    /// one `HidApi` (e.g. in an `Arc`) can list devices at the same time without a
+    /// `Mutex`. No backend keeps enumeration state in the `HidApi`, the native linux
+    /// and windows backends also do not share any between calls. With the hidapi C
+    /// library the last error message is global, so concurrent failing calls may
+    /// report each other's errors.
+    pub fn enumerate(&self, vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
+        #[cfg(feature = "mock")]
+        if let Some(devices) = &self.mock_devices {
+            return Ok(devices
+                .iter()
+                .map(|device| &device.info)
+                .filter(|info| {
+                    (vid == 0 || info.vendor_id == vid) && (pid == 0 || info.product_id == pid)
+                })
+                .cloned()
+                .collect());
+        }
+
+        HidApiBackend::get_hid_device_info_vector(vid, pid)
+    }
+
+    /// Check if the process is allowed to read Input reports from HID devices.
+    ///
+    /// On macOS reading from keyboards and other devices which generate input
+    /// events requires the Input Monitoring permission. Without it, opening such
+    /// a device succeeds but reads never return any data. This uses
+    /// `IOHIDCheckAccess`, and returns [`AccessStatus::Unknown`] on macOS versions
+    /// before 10.15, which do not have it.
+    ///
+    /// Other platforms have no such permission, so this always returns
+    /// [`AccessStatus::Granted`]. Access to individual devices may still be denied,
+    /// e.g. on Linux without the right udev rules.
+    pub fn check_access(&self) -> AccessStatus {
+        #[cfg(target_os = "macos")]
+        return macos::check_access();
+        #[cfg(not(target_os = "macos"))]
+        AccessStatus::Granted
+    }
+
+    /// Ask the user for the permission checked by [`HidApi::check_access`].
+    ///
+    /// On macOS this uses `IOHIDRequestAccess`, which shows the system prompt for
+    /// the Input Monitoring permission the first time it is called, and returns
+    /// if access is granted. This should be called early, before opening devices:
+    /// devices opened without the permission may keep returning no data until
+    /// they are opened again, or until the application is restarted. Fails on
+    /// macOS versions before 10.15.
+    ///
+    /// Other platforms have no such permission, so this always returns `Ok(true)`.
+    pub fn request_access(&self) -> HidResult<bool> {
+        #[cfg(target_os = "macos")]
+        return macos::request_access();
+        #[cfg(not(target_os = "macos"))]
+        Ok(true)
+    }
+
+    /// Get the optional features supported by the backend in use.
+    ///
+    /// This allows feature detection in cross-platform code, instead of
+    /// hardcoding which backend supports what.
+    pub fn capabilities(&self) -> Capabilities {
+        #[cfg(feature = "mock")]
+        if self.mock_devices.is_some() {
+            return mock::CAPABILITIES;
+        }
+
+        HidApiBackend::CAPABILITIES
+    }
+
     /// Returns iterator containing information about attached HID devices
     /// that have been indexed, either by `refresh_devices` or `add_devices`.
     pub fn device_list(&self) -> impl Iterator<Item = &DeviceInfo> {
         self.device_list.iter()
     }
 
+    /// Returns the indexed devices together with their report descriptors.
+    ///
+    /// On the `linux-native` backends the descriptors are read from sysfs, all
+    /// other backends have to open each device to get it. Devices for which the
+    /// descriptor could not be read are left out.
+    pub fn device_list_with_descriptors(&self) -> Vec<(DeviceInfo, Vec<u8>)> {
+        self.device_list()
+            .filter_map(|info| {
+                let descriptor = self.read_report_descriptor(info).ok()?;
+                Some((info.clone(), descriptor))
+            })
+            .collect()
+    }
+
+    fn read_report_descriptor(&self, info: &DeviceInfo) -> HidResult<Vec<u8>> {
+        #[cfg(all(
+            any(feature = "linux-native", feature = "linux-native-sysfs"),
+            target_os = "linux"
+        ))]
+        {
+            #[cfg(feature = "mock")]
+            let mocked = self.mock_devices.is_some();
+            #[cfg(not(feature = "mock"))]
+            let mocked = false;
+
+            if !mocked {
+                return HidApiBackend::get_report_descriptor(info.path());
+            }
+        }
+
+        info.open_device(self)?.get_report_descriptor_vec()
+    }
+
+    /// Returns the indexed devices grouped by the physical device they belong to.
+    ///
+    /// A device with multiple HID interfaces (or top level collections) shows up
+    /// as multiple entries in `device_list()`, but as a single group here.
+    ///
+    /// The devices are grouped by vendor ID, product ID and serial number, so
+    /// multiple identical devices without a serial number end up in one group.
+    pub fn device_groups(&self) -> Vec<DeviceGroup> {
+        group_devices(self.device_list())
+    }
+
+    /// Block until a device with the given Vendor ID (VID) and Product ID (PID)
+    /// becomes available.
+    ///
+    /// The device list is refreshed (as with `refresh_devices()`) with a growing
+    /// interval of up to one second between attempts, until a matching device
+    /// shows up or `timeout` elapses. A `timeout` of `None` waits forever.
+    ///
+    /// If successful, returns the [`DeviceInfo`] of the first matching device.
+    /// If the device did not show up in time, returns [`HidError::Timeout`].
+    pub fn wait_for_device(
+        &mut self,
+        vid: u16,
+        pid: u16,
+        timeout: Option<Duration>,
+    ) -> HidResult<DeviceInfo> {
+        const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut interval = Duration::from_millis(10);
+
+        loop {
+            self.refresh_devices()?;
+            if let Some(info) = self
+                .device_list
+                .iter()
+                .find(|info| info.vendor_id == vid && info.product_id == pid)
+            {
+                return Ok(info.clone());
+            }
+
+            let sleep = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(HidError::Timeout);
+                    }
+                    interval.min(remaining)
+                }
+                None => interval,
+            };
+            thread::sleep(sleep);
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
     /// Open a HID device using a Vendor ID (VID) and Product ID (PID).
     ///
     /// When multiple devices with the same vid and pid are available, then the
     /// first one found in the internal device list will be used. There are however
     /// no guarantees, which device this will be.
+    ///
+    /// A device with multiple top level collections is listed once per usage in
+    /// `device_list()`. Depending on the backend, these entries share a path and
+    /// the opened handle covers all of them, or each is a separate handle. Use
+    /// [`HidApi::open_usage`] to open a specific collection.
     pub fn open(&self, vid: u16, pid: u16) -> HidResult<HidDevice> {
+        #[cfg(feature = "mock")]
+        if let Some(devices) = &self.mock_devices {
+            return mock::open(devices, |info| {
+                info.vendor_id == vid && info.product_id == pid
+            })
+            .ok_or_else(|| HidError::device_not_found(vid, pid, None));
+        }
+
         let dev = HidApiBackend::open(vid, pid)?;
         Ok(HidDevice::from_backend(Box::new(dev)))
     }
 
     /// Open a HID device using a Vendor ID (VID), Product ID (PID) and
     /// a serial number.
+    ///
+    /// This allows picking a specific one of multiple identical devices, which
+    /// only differ by their serial number.
     pub fn open_serial(&self, vid: u16, pid: u16, sn: &str) -> HidResult<HidDevice> {
+        #[cfg(feature = "mock")]
+        if let Some(devices) = &self.mock_devices {
+            return mock::open(devices, |info| {
+                info.vendor_id == vid && info.product_id == pid && info.serial_number() == Some(sn)
+            })
+            .ok_or_else(|| HidError::device_not_found(vid, pid, Some(sn)));
+        }
+
         let dev = HidApiBackend::open_serial(vid, pid, sn)?;
         Ok(HidDevice::from_backend(Box::new(dev)))
     }
 
+    /// Like [`HidApi::open`] and [`HidApi::open_serial`], but gives up with
+    /// [`HidError::Timeout`] if opening the device takes longer than `timeout`.
+    ///
+    /// Opening a device by vid and pid enumerates all devices first, which can be
+    /// slow on a busy system. The deadline is checked between the devices of the
+    /// enumeration and before the device is opened. The C library can not be
+    /// interrupted in `hid_enumerate()`, so there the deadline is only checked
+    /// after it. A `timeout` of `None` waits forever, like `open()`.
+    pub fn open_timeout(
+        &self,
+        vid: u16,
+        pid: u16,
+        serial_number: Option<&str>,
+        timeout: Option<Duration>,
+    ) -> HidResult<HidDevice> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        #[cfg(feature = "mock")]
+        if self.mock_devices.is_some() {
+            check_deadline(deadline)?;
+            return match serial_number {
+                Some(sn) => self.open_serial(vid, pid, sn),
+                None => self.open(vid, pid),
+            };
+        }
+
+        let dev = HidApiBackend::open_deadline(vid, pid, serial_number, deadline)?;
+        Ok(HidDevice::from_backend(Box::new(dev)))
+    }
+
     /// The path name be determined by inspecting the device list available with [HidApi::devices()](struct.HidApi.html#method.devices)
     ///
     /// Alternatively a platform-specific path name can be used (eg: /dev/hidraw0 on Linux).
     pub fn open_path(&self, device_path: &CStr) -> HidResult<HidDevice> {
+        #[cfg(feature = "mock")]
+        if let Some(devices) = &self.mock_devices {
+            return mock::open_path(devices, device_path);
+        }
+
         let dev = HidApiBackend::open_path(device_path)?;
         Ok(HidDevice::from_backend(Box::new(dev)))
     }
 
+    /// Open the device at the given path, choosing whether its file descriptor
+    /// is blocking.
+    ///
+    /// [`HidApi::open_path`] opens hidraw nodes with `O_NONBLOCK`, so writes to a
+    /// busy device fail instead of waiting. With `blocking` set, writes wait until
+    /// the kernel accepts the report. Reads always wait with poll(2) first, so the
+    /// blocking mode of [`HidDevice::read`] is still chosen with
+    /// [`HidDevice::set_blocking_mode`]. Only if another reader of the same fd takes
+    /// the report between the poll and the read, the read waits for the next report,
+    /// regardless of timeouts and a [`ReadCanceller`].
+    #[cfg(all(
+        any(feature = "linux-native", feature = "linux-native-sysfs"),
+        target_os = "linux"
+    ))]
+    pub fn open_path_with_flags(&self, device_path: &CStr, blocking: bool) -> HidResult<HidDevice> {
+        #[cfg(feature = "mock")]
+        if let Some(devices) = &self.mock_devices {
+            return mock::open_path(devices, device_path);
+        }
+
+        let dev = HidApiBackend::open_path_with_flags(device_path, blocking)?;
+        Ok(HidDevice::from_backend(Box::new(dev)))
+    }
+
+    /// Open a hidraw device from a file descriptor which was opened elsewhere.
+    ///
+    /// This is meant for sandboxed applications, which can not open `/dev/hidraw*`
+    /// themselves but receive a file descriptor from a broker or portal. The fd
+    /// is not reopened, the returned device takes ownership of it and closes it
+    /// when dropped. A `RawFd` can be converted with `OwnedFd::from_raw_fd`.
+    ///
+    /// If sysfs is not accessible, the device info is gathered with ioctls instead,
+    /// which lack the USB string descriptors and the interface number.
+    #[cfg(all(
+        any(feature = "linux-native", feature = "linux-native-sysfs"),
+        target_os = "linux"
+    ))]
+    pub fn open_fd(&self, fd: std::os::fd::OwnedFd) -> HidResult<HidDevice> {
+        let dev = HidApiBackend::open_fd(fd)?;
+        Ok(HidDevice::from_backend(Box::new(dev)))
+    }
+
+    /// Open the only device with the given vid and pid, or return an error if
+    /// multiple devices match.
+    ///
+    /// Unlike [`HidApi::open`], this never silently picks one of multiple identical
+    /// devices, the caller has to tell them apart with [`HidApi::open_serial`] instead.
+    /// The device is looked up in the internal device list, where the interfaces and
+    /// collections of one physical device are recognized by their common serial number.
+    pub fn open_unique(&self, vid: u16, pid: u16) -> HidResult<HidDevice> {
+        unique_device(vid, pid, self.device_list())?.open_device(self)
+    }
+
+    /// Open all devices in the internal device list which match the given vid and
+    /// pid, `None` matches any.
+    ///
+    /// Failing to open one device does not stop the others from being opened, the
+    /// result of each is returned together with its [`DeviceInfo`].
+    pub fn open_all(
+        &self,
+        vid: Option<u16>,
+        pid: Option<u16>,
+    ) -> Vec<(DeviceInfo, HidResult<HidDevice>)> {
+        self.device_list()
+            .filter(|info| {
+                (vid.is_none() || vid == Some(info.vendor_id))
+                    && (pid.is_none() || pid == Some(info.product_id))
+            })
+            .map(|info| (info.clone(), info.open_device(self)))
+            .collect()
+    }
+
+    /// Open the device with the given vid and pid, which has a top level collection
+    /// with the given usage page and usage.
+    ///
+    /// The device is looked up in the internal device list.
+    pub fn open_usage(
+        &self,
+        vid: u16,
+        pid: u16,
+        usage_page: u16,
+        usage: u16,
+    ) -> HidResult<HidDevice> {
+        self.open_filtered(vid, pid, None, Some(usage_page), Some(usage))
+    }
+
+    /// Open the first device with the given vid and pid which matches all of the
+    /// other criteria that are not `None`.
+    ///
+    /// This tells apart the collections of composite devices like a combined
+    /// keyboard and mouse receiver, which share vid, pid and serial number and only
+    /// differ by usage page and usage. The device is looked up in the internal
+    /// device list.
+    pub fn open_filtered(
+        &self,
+        vid: u16,
+        pid: u16,
+        serial: Option<&str>,
+        usage_page: Option<u16>,
+        usage: Option<u16>,
+    ) -> HidResult<HidDevice> {
+        match self.device_list().find(|info| {
+            (info.vendor_id, info.product_id) == (vid, pid)
+                && (serial.is_none() || serial == info.serial_number())
+                && (usage_page.is_none() || usage_page == Some(info.usage_page))
+                && (usage.is_none() || usage == Some(info.usage))
+        }) {
+            Some(info) => info.open_device(self),
+            None => Err(HidError::DeviceNotFound {
+                vendor_id: vid,
+                product_id: pid,
+                serial_number: serial.map(str::to_string),
+                usage_page,
+                usage,
+            }),
+        }
+    }
+
+    /// Open the device identified by `id`, see [`DeviceId`].
+    ///
+    /// The device is looked up in the internal device list, so call
+    /// [`HidApi::refresh_devices`] first if the device might have been
+    /// reconnected since then.
+    pub fn open_id(&self, id: &DeviceId) -> HidResult<HidDevice> {
+        match self.device_list().find(|info| id.matches(info)) {
+            Some(info) => info.open_device(self),
+            None => Err(HidError::DeviceNotFound {
+                vendor_id: id.vendor_id,
+                product_id: id.product_id,
+                serial_number: id.serial_number.clone(),
+                usage_page: Some(id.usage_page),
+                usage: Some(id.usage),
+            }),
+        }
+    }
+
     /// Open a HID device using libusb_wrap_sys_device.
     #[cfg(libusb)]
     pub fn wrap_sys_device(&self, sys_dev: isize, interface_num: i32) -> HidResult<HidDevice> {
@@ -304,26 +742,94 @@ impl HidApi {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 enum WcharString {
     String(String),
-    #[cfg_attr(all(feature = "linux-native", target_os = "linux"), allow(dead_code))]
+    #[cfg_attr(
+        all(
+            any(feature = "linux-native", feature = "linux-native-sysfs"),
+            target_os = "linux"
+        ),
+        allow(dead_code)
+    )]
     Raw(Vec<wchar_t>),
     None,
 }
 
+impl WcharString {
+    /// Convert into a `String`, replacing characters which can not be decoded
+    /// with U+FFFD.
+    fn to_string_lossy(&self) -> Option<String> {
+        match self {
+            WcharString::String(s) => Some(s.clone()),
+            WcharString::Raw(raw) => Some(wchar_to_string_lossy(raw)),
+            WcharString::None => None,
+        }
+    }
+}
+
+/// Serialized as a plain (possibly lossily converted) string, or `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for WcharString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_string_lossy().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WcharString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<String>::deserialize(deserializer)? {
+            Some(s) => WcharString::String(s),
+            None => WcharString::None,
+        })
+    }
+}
+
+/// Serializes the device path as a plain string instead of a byte array.
+#[cfg(feature = "serde")]
+mod path_as_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::ffi::CString;
+
+    pub fn serialize<S: Serializer>(path: &CString, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&path.to_string_lossy())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CString, D::Error> {
+        CString::new(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Decode raw `wchar_t`s, which are UTF-16 on Windows and UTF-32 everywhere else.
+#[cfg(target_os = "windows")]
+fn wchar_to_string_lossy(raw: &[wchar_t]) -> String {
+    String::from_utf16_lossy(raw)
+}
+
+/// Decode raw `wchar_t`s, which are UTF-16 on Windows and UTF-32 everywhere else.
+#[cfg(not(target_os = "windows"))]
+fn wchar_to_string_lossy(raw: &[wchar_t]) -> String {
+    raw.iter()
+        .map(|&c| char::from_u32(c as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Strings which can not be decoded are converted lossily, so a malformed
+/// string descriptor does not hide the rest of it.
 impl From<WcharString> for Option<String> {
     fn from(val: WcharString) -> Self {
         match val {
             WcharString::String(s) => Some(s),
-            _ => None,
+            val => val.to_string_lossy(),
         }
     }
 }
 
 /// The underlying HID bus type.
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BusType {
     Unknown = 0x00,
     Usb = 0x01,
@@ -332,13 +838,65 @@ pub enum BusType {
     Spi = 0x04,
 }
 
+impl BusType {
+    /// Convert a Linux kernel bus type (the `BUS_*` values from `linux/input.h`).
+    ///
+    /// Bus types without a matching variant are mapped to [`BusType::Unknown`].
+    pub fn from_linux_bustype(bus: u32) -> Self {
+        // Bus values from linux/input.h
+        const BUS_USB: u32 = 0x03;
+        const BUS_BLUETOOTH: u32 = 0x05;
+        const BUS_I2C: u32 = 0x18;
+        const BUS_SPI: u32 = 0x1C;
+
+        match bus {
+            BUS_USB => BusType::Usb,
+            BUS_BLUETOOTH => BusType::Bluetooth,
+            BUS_I2C => BusType::I2c,
+            BUS_SPI => BusType::Spi,
+            _ => BusType::Unknown,
+        }
+    }
+
+    /// Convert a macOS IOKit transport string (the `Transport` property of a HID device).
+    ///
+    /// Transports without a matching variant are mapped to [`BusType::Unknown`].
+    pub fn from_transport_str(transport: &str) -> Self {
+        match transport {
+            "USB" => BusType::Usb,
+            "Bluetooth" | "BluetoothLowEnergy" => BusType::Bluetooth,
+            "I2C" => BusType::I2c,
+            "SPI" => BusType::Spi,
+            _ => BusType::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for BusType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BusType::Unknown => "Unknown",
+            BusType::Usb => "USB",
+            BusType::Bluetooth => "Bluetooth",
+            BusType::I2c => "I2C",
+            BusType::Spi => "SPI",
+        };
+        f.write_str(name)
+    }
+}
+
 /// Device information. Use accessors to extract information about Hid devices.
 ///
+/// The strings are read during enumeration, so e.g. `product_string()` can be used
+/// to display a device without having to open it first.
+///
 /// Note: Methods like `serial_number()` may return None, if the conversion to a
 /// String failed internally. You can however access the raw hid representation of the
 /// string by calling `serial_number_raw()`
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceInfo {
+    #[cfg_attr(feature = "serde", serde(with = "path_as_string"))]
     path: CString,
     vendor_id: u16,
     product_id: u16,
@@ -346,15 +904,41 @@ pub struct DeviceInfo {
     release_number: u16,
     manufacturer_string: WcharString,
     product_string: WcharString,
+    /// Only populated by backends which have a separate kernel name
+    raw_name: WcharString,
     #[allow(dead_code)]
     usage_page: u16,
     #[allow(dead_code)]
     usage: u16,
     interface_number: i32,
     bus_type: BusType,
+    /// bCountryCode of the HID descriptor, if the backend can read it
+    #[cfg_attr(feature = "serde", serde(default))]
+    country_code: Option<u8>,
 }
 
 impl DeviceInfo {
+    /// A USB device without strings and usage, for the mock backend and tests to
+    /// fill in
+    #[cfg(any(test, feature = "mock"))]
+    pub(crate) fn new(path: CString, vendor_id: u16, product_id: u16) -> Self {
+        DeviceInfo {
+            path,
+            vendor_id,
+            product_id,
+            serial_number: WcharString::None,
+            release_number: 0,
+            manufacturer_string: WcharString::None,
+            product_string: WcharString::None,
+            raw_name: WcharString::None,
+            usage_page: 0,
+            usage: 0,
+            interface_number: -1,
+            bus_type: BusType::Usb,
+            country_code: None,
+        }
+    }
+
     pub fn path(&self) -> &CStr {
         &self.path
     }
@@ -382,6 +966,12 @@ impl DeviceInfo {
         }
     }
 
+    /// Like `serial_number()`, but a string which can not be decoded is converted
+    /// with U+FFFD replacing the invalid characters, instead of returning `None`.
+    pub fn serial_number_lossy(&self) -> Option<String> {
+        self.serial_number.to_string_lossy()
+    }
+
     pub fn release_number(&self) -> u16 {
         self.release_number
     }
@@ -401,6 +991,12 @@ impl DeviceInfo {
         }
     }
 
+    /// Like `manufacturer_string()`, but a string which can not be decoded is converted
+    /// with U+FFFD replacing the invalid characters, instead of returning `None`.
+    pub fn manufacturer_string_lossy(&self) -> Option<String> {
+        self.manufacturer_string.to_string_lossy()
+    }
+
     /// Try to call `product_string_raw()`, if None is returned.
     pub fn product_string(&self) -> Option<&str> {
         match self.product_string {
@@ -416,6 +1012,25 @@ impl DeviceInfo {
         }
     }
 
+    /// Like `product_string()`, but a string which can not be decoded is converted
+    /// with U+FFFD replacing the invalid characters, instead of returning `None`.
+    pub fn product_string_lossy(&self) -> Option<String> {
+        self.product_string.to_string_lossy()
+    }
+
+    /// The name the kernel uses for the device on the `linux-native` backends
+    /// (as returned by `HIDIOCGRAWNAME`), e.g. "Logitech G923 Racing Wheel".
+    ///
+    /// This can be used to tell product variants apart that share a vid/pid.
+    /// All other backends return the product string.
+    pub fn raw_name(&self) -> Option<&str> {
+        match self.raw_name {
+            WcharString::String(ref s) => Some(s),
+            WcharString::None => self.product_string(),
+            _ => None,
+        }
+    }
+
     /// Usage page is not available on linux libusb backends
     #[cfg(not(all(libusb, target_os = "linux")))]
     pub fn usage_page(&self) -> u16 {
@@ -432,10 +1047,94 @@ impl DeviceInfo {
         self.interface_number
     }
 
+    /// Get the country code from the HID descriptor of the device.
+    ///
+    /// For keyboards this identifies the layout of the keycaps, 0 means the
+    /// hardware is not localized (see section 6.2.1 of the HID specification).
+    /// Many devices report 0 regardless. This is only available on the
+    /// `linux-native` backends for USB devices, `None` everywhere else.
+    pub fn country_code(&self) -> Option<u8> {
+        self.country_code
+    }
+
     pub fn bus_type(&self) -> BusType {
         self.bus_type
     }
 
+    /// Get an identifier for the device which, unlike the path, stays the same
+    /// across reconnects and reboots.
+    pub fn id(&self) -> DeviceId {
+        DeviceId {
+            vendor_id: self.vendor_id,
+            product_id: self.product_id,
+            serial_number: self.serial_number.to_string_lossy(),
+            usage_page: self.usage_page,
+            usage: self.usage,
+        }
+    }
+
+    /// Check if both entries belong to the same device interface, which may be
+    /// listed once for each of its top level collections.
+    ///
+    /// Unlike `==`, this ignores the usage page and usage.
+    pub fn same_device(&self, other: &DeviceInfo) -> bool {
+        self.path == other.path && self.serial_number == other.serial_number
+    }
+
+    /// Produce a multi-line, human-readable description of the device.
+    ///
+    /// The format is stable and meant to be pasted into bug reports.
+    pub fn summary(&self) -> String {
+        let serial_number = self
+            .serial_number
+            .to_string_lossy()
+            .unwrap_or_else(|| "(none)".into());
+
+        let mut summary = String::new();
+        self.write_summary(&mut summary, &serial_number)
+            .expect("writing to a String can not fail");
+        summary
+    }
+
+    /// Get a wrapper, whose `Display` and `Debug` implementations mask all but the
+    /// last 4 characters of the serial number. At least half of the characters
+    /// are masked, so short serial numbers do not leak either.
+    ///
+    /// This allows logging device details without leaking serial numbers.
+    /// `Display` produces the same format as [`DeviceInfo::summary`].
+    pub fn redacted(&self) -> RedactedDeviceInfo<'_> {
+        RedactedDeviceInfo(self)
+    }
+
+    fn write_summary(&self, f: &mut impl fmt::Write, serial_number: &str) -> fmt::Result {
+        let string = |s: &WcharString| s.to_string_lossy().unwrap_or_else(|| "(none)".into());
+
+        write!(
+            f,
+            "Device {:04x}:{:04x}\n  \
+             Manufacturer: {}\n  \
+             Product: {}\n  \
+             Serial number: {}\n  \
+             Release number: {:#06x}\n  \
+             Usage page: {:#06x}\n  \
+             Usage: {:#06x}\n  \
+             Interface number: {}\n  \
+             Bus type: {}\n  \
+             Path: {}",
+            self.vendor_id,
+            self.product_id,
+            string(&self.manufacturer_string),
+            string(&self.product_string),
+            serial_number,
+            self.release_number,
+            self.usage_page,
+            self.usage,
+            self.interface_number,
+            self.bus_type,
+            self.path.to_string_lossy(),
+        )
+    }
+
     /// Use the information contained in `DeviceInfo` to open
     /// and return a handle to a [HidDevice](struct.HidDevice.html).
     ///
@@ -458,6 +1157,28 @@ impl DeviceInfo {
     }
 }
 
+/// Two entries are equal if they have the same path, serial number, usage page and
+/// usage, i.e. they describe the same top level collection of the same device.
+///
+/// The other fields are not compared, so entries from different enumerations can be
+/// stored in a `HashSet` to find the devices which were added or removed.
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_device(other) && (self.usage_page, self.usage) == (other.usage_page, other.usage)
+    }
+}
+
+impl Eq for DeviceInfo {}
+
+impl std::hash::Hash for DeviceInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        self.serial_number.hash(state);
+        self.usage_page.hash(state);
+        self.usage.hash(state);
+    }
+}
+
 impl fmt::Debug for DeviceInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HidDeviceInfo")
@@ -467,6 +1188,235 @@ impl fmt::Debug for DeviceInfo {
     }
 }
 
+/// A [`DeviceInfo`] which is formatted with a masked serial number, see
+/// [`DeviceInfo::redacted`].
+pub struct RedactedDeviceInfo<'a>(&'a DeviceInfo);
+
+impl RedactedDeviceInfo<'_> {
+    fn serial_number(&self) -> String {
+        let serial_number = match self.0.serial_number.to_string_lossy() {
+            Some(serial_number) => serial_number,
+            None => return "(none)".into(),
+        };
+
+        // At most the last 4 characters, and never more than half of them
+        let len = serial_number.chars().count();
+        let shown = (len / 2).min(4);
+        serial_number
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i + shown < len { '*' } else { c })
+            .collect()
+    }
+}
+
+impl fmt::Display for RedactedDeviceInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_summary(f, &self.serial_number())
+    }
+}
+
+impl fmt::Debug for RedactedDeviceInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HidDeviceInfo")
+            .field("vendor_id", &self.0.vendor_id)
+            .field("product_id", &self.0.product_id)
+            .field("serial_number", &self.serial_number())
+            .finish()
+    }
+}
+
+/// All entries of the device list belonging to one physical device, see
+/// [`HidApi::device_groups`].
+#[derive(Clone, Debug)]
+pub struct DeviceGroup {
+    vendor_id: u16,
+    product_id: u16,
+    serial_number: Option<String>,
+    devices: Vec<DeviceInfo>,
+}
+
+impl DeviceGroup {
+    pub fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    pub fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// The interfaces of the device, in enumeration order.
+    pub fn devices(&self) -> &[DeviceInfo] {
+        &self.devices
+    }
+}
+
+/// Get the `(added, removed)` devices between two device lists
+fn device_list_delta(
+    previous: &[DeviceInfo],
+    current: &[DeviceInfo],
+) -> (Vec<DeviceInfo>, Vec<DeviceInfo>) {
+    // The usage is part of the equality because devices with multiple top level
+    // collections are listed once per usage, with the same path
+    let missing_from = |list: &[DeviceInfo], info: &&DeviceInfo| !list.contains(info);
+
+    let added = current
+        .iter()
+        .filter(|info| missing_from(previous, info))
+        .cloned()
+        .collect();
+    let removed = previous
+        .iter()
+        .filter(|info| missing_from(current, info))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+fn group_devices<'a>(devices: impl Iterator<Item = &'a DeviceInfo>) -> Vec<DeviceGroup> {
+    let mut groups: Vec<DeviceGroup> = Vec::new();
+
+    for info in devices {
+        let serial_number = info.serial_number.to_string_lossy();
+        match groups.iter_mut().find(|group| {
+            group.vendor_id == info.vendor_id
+                && group.product_id == info.product_id
+                && group.serial_number == serial_number
+        }) {
+            Some(group) => group.devices.push(info.clone()),
+            None => groups.push(DeviceGroup {
+                vendor_id: info.vendor_id,
+                product_id: info.product_id,
+                serial_number,
+                devices: vec![info.clone()],
+            }),
+        }
+    }
+
+    groups
+}
+
+/// Reject an empty buffer up front, some backends index into it
+/// unconditionally.
+fn check_buffer(buf: &[u8]) -> HidResult<()> {
+    if buf.is_empty() {
+        return Err(HidError::InvalidZeroSizeData);
+    }
+    Ok(())
+}
+
+/// Fail with [`HidError::Timeout`] once `deadline` has passed, for the backends
+/// to call between the steps of enumerating and opening a device.
+fn check_deadline(deadline: Option<Instant>) -> HidResult<()> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(HidError::Timeout),
+        _ => Ok(()),
+    }
+}
+
+/// Find the single physical device with the given vid and pid among `devices`.
+fn unique_device<'a>(
+    vid: u16,
+    pid: u16,
+    devices: impl Iterator<Item = &'a DeviceInfo>,
+) -> HidResult<&'a DeviceInfo> {
+    let devices: Vec<_> = devices
+        .filter(|info| (info.vendor_id, info.product_id) == (vid, pid))
+        .collect();
+    let Some(first) = devices.first() else {
+        return Err(HidError::device_not_found(vid, pid, None));
+    };
+
+    // Without serial numbers identical devices end up in the same group, but one
+    // device never lists the same interface and usage twice
+    let groups = group_devices(devices.iter().copied());
+    let duplicate = devices.iter().enumerate().any(|(i, a)| {
+        devices[..i].iter().any(|b| {
+            (a.interface_number, a.usage_page, a.usage)
+                == (b.interface_number, b.usage_page, b.usage)
+        })
+    });
+    if groups.len() > 1 || duplicate {
+        return Err(HidError::HidApiError {
+            message: "multiple devices match".to_string(),
+        });
+    }
+
+    Ok(first)
+}
+
+/// A durable identifier for a device, which can be stored to open the same device
+/// again later with [`HidApi::open_id`].
+///
+/// Devices without a serial number can only be told apart by their usage, so multiple
+/// identical ones may match the same `DeviceId`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceId {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub serial_number: Option<String>,
+    pub usage_page: u16,
+    pub usage: u16,
+}
+
+impl DeviceId {
+    /// Check if the device described by `info` is the one identified by `self`.
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        *self == info.id()
+    }
+}
+
+/// Cancels blocking reads of a [`HidDevice`], see [`HidDevice::read_canceller`].
+///
+/// Unlike the device itself, this can be shared with other threads.
+#[derive(Clone)]
+pub struct ReadCanceller {
+    cancel: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl ReadCanceller {
+    #[cfg_attr(
+        not(all(
+            any(feature = "linux-native", feature = "linux-native-sysfs"),
+            target_os = "linux"
+        )),
+        allow(dead_code)
+    )]
+    fn new(cancel: impl Fn() + Send + Sync + 'static) -> Self {
+        Self {
+            cancel: Arc::new(cancel),
+        }
+    }
+
+    /// Make the read currently in progress, or the next one if there is none,
+    /// return [`HidError::Cancelled`].
+    pub fn cancel(&self) {
+        (self.cancel)()
+    }
+}
+
+/// The type of a HID report, see [`HidDevice::set_report`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ReportType {
+    Input,
+    Output,
+    Feature,
+}
+
+/// Whether the process may read Input reports, see [`HidApi::check_access`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AccessStatus {
+    Granted,
+    Denied,
+    /// The user has not been asked yet, or the status can not be determined
+    Unknown,
+}
+
 /// Trait which the different backends must implement
 trait HidDeviceBackendBase {
     #[cfg(hidapi)]
@@ -480,6 +1430,7 @@ trait HidDeviceBackendBase {
     #[cfg(any(hidapi, target_os = "linux"))]
     fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize>;
     fn set_blocking_mode(&self, blocking: bool) -> HidResult<()>;
+    fn is_blocking(&self) -> bool;
     fn get_device_info(&self) -> HidResult<DeviceInfo>;
     fn get_manufacturer_string(&self) -> HidResult<Option<String>>;
     fn get_product_string(&self) -> HidResult<Option<String>>;
@@ -491,10 +1442,99 @@ trait HidDeviceBackendBase {
             message: "get_indexed_string: not supported".to_string(),
         })
     }
+
+    fn set_input_report(&self, _data: &[u8]) -> HidResult<()> {
+        Err(HidError::HidApiError {
+            message: "set_input_report: not supported".to_string(),
+        })
+    }
+
+    fn read_canceller(&self) -> HidResult<ReadCanceller> {
+        Err(HidError::HidApiError {
+            message: "read_canceller: not supported".to_string(),
+        })
+    }
+
+    fn has_pending_report(&self) -> HidResult<bool> {
+        Err(HidError::HidApiError {
+            message: "has_pending_report: not supported".to_string(),
+        })
+    }
+
+    fn queued_report_count(&self) -> HidResult<usize> {
+        Err(HidError::HidApiError {
+            message: "queued_report_count: not supported".to_string(),
+        })
+    }
+
+    #[cfg(all(
+        any(feature = "linux-native", feature = "linux-native-sysfs"),
+        target_os = "linux"
+    ))]
+    fn into_fd(self: Box<Self>) -> HidResult<std::os::fd::OwnedFd> {
+        Err(HidError::HidApiError {
+            message: "into_fd: not supported".to_string(),
+        })
+    }
+
+    #[cfg(feature = "linux-raw-ioctl")]
+    unsafe fn ioctl_raw(&self, _request: u32, _buf: &mut [u8]) -> HidResult<i32> {
+        Err(HidError::HidApiError {
+            message: "ioctl_raw: not supported".to_string(),
+        })
+    }
 }
 
 pub struct HidDevice {
     inner: Box<dyn HidDeviceBackend>,
+    stats: Cell<HidStats>,
+    descriptor: RefCell<Option<ReportDescriptor>>,
+    /// Reused by `read_report()` to read numbered reports with their Report ID
+    report_buf: RefCell<Vec<u8>>,
+    opened: Instant,
+}
+
+/// The optional features supported by the backend, see [`HidApi::capabilities`].
+///
+/// Calling a method which is not supported returns an error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// [`HidDevice::send_feature_report`] and [`HidDevice::get_feature_report`]
+    pub supports_feature_reports: bool,
+    /// [`HidDevice::get_input_report`]
+    pub supports_get_input_report: bool,
+    /// [`HidDevice::set_report`] with [`ReportType::Input`]
+    pub supports_set_input_report: bool,
+    /// [`HidDevice::get_report_descriptor`]
+    pub supports_report_descriptor: bool,
+    /// [`HidDevice::read_canceller`]
+    pub supports_read_canceller: bool,
+    /// [`HidDevice::has_pending_report`]
+    pub supports_pending_report_check: bool,
+    /// [`HidDevice::get_indexed_string`]
+    pub supports_indexed_strings: bool,
+    /// Notifications when devices are connected or disconnected, which no backend
+    /// supports yet; poll with [`HidApi::enumerate_delta`] instead
+    pub supports_hotplug: bool,
+    /// USB control transfers to the device, which no backend supports yet
+    pub supports_control_transfer: bool,
+}
+
+/// Statistics about the Input reports read from a [`HidDevice`], see [`HidDevice::stats`].
+///
+/// Reports which the operating system drops before they are read, e.g. because
+/// the application reads too slowly, can not be detected and are not counted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HidStats {
+    /// Number of successful reads which returned a report
+    pub reports_received: u64,
+    /// Total size of all reports received
+    pub bytes_received: u64,
+    /// Number of reads which failed
+    pub read_errors: u64,
+    /// When the last report was received
+    pub last_report: Option<Instant>,
 }
 
 impl Debug for HidDevice {
@@ -504,8 +1544,40 @@ impl Debug for HidDevice {
 }
 
 impl HidDevice {
+    /// The largest report descriptor the backends can read, the same as
+    /// [`MAX_REPORT_DESCRIPTOR_SIZE`]. This is enough for [`HidDevice::get_report_descriptor`].
+    pub const MAX_REPORT_DESCRIPTOR_SIZE: usize = MAX_REPORT_DESCRIPTOR_SIZE;
+
+    /// How many Input reports are buffered for a device which is not read from.
+    ///
+    /// When the buffer is full, reports are dropped. On Linux the kernel drops the
+    /// new reports, the other platforms drop the oldest ones. [`HidDevice::stats`]
+    /// and [`HidDevice::flush_input`] help with applications which read slowly.
+    pub const INPUT_QUEUE_SIZE: usize = HidApiBackend::INPUT_QUEUE_SIZE;
+
     fn from_backend(inner: Box<dyn HidDeviceBackend>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            stats: Cell::default(),
+            descriptor: RefCell::new(None),
+            report_buf: RefCell::default(),
+            opened: Instant::now(),
+        }
+    }
+
+    fn record_read(&self, res: HidResult<usize>) -> HidResult<usize> {
+        let mut stats = self.stats.get();
+        match res {
+            Ok(0) | Err(HidError::Cancelled) => {}
+            Ok(len) => {
+                stats.reports_received += 1;
+                stats.bytes_received += len as u64;
+                stats.last_report = Some(Instant::now());
+            }
+            Err(_) => stats.read_errors += 1,
+        }
+        self.stats.set(stats);
+        res
     }
 }
 
@@ -539,11 +1611,45 @@ impl HidDevice {
     /// one exists. If it does not, it will send the data through
     /// the Control Endpoint (Endpoint 0).
     ///
-    /// If successful, returns the actual number of bytes written.
+    /// If successful, returns the actual number of bytes written. If the
+    /// device accepted fewer bytes than `data.len()`,
+    /// [`HidError::IncompleteSendError`] is returned instead. On macOS the
+    /// report is sent with `IOHIDDeviceSetReport`, which either transfers the
+    /// whole report or fails, so a partial write is never reported there.
     pub fn write(&self, data: &[u8]) -> HidResult<usize> {
         self.inner.write(data)
     }
 
+    /// Write an Output report to a device which does not use numbered reports,
+    /// sending `data` exactly as given.
+    ///
+    /// `write()` always takes the first byte as the Report ID, and for devices without
+    /// numbered reports it is not sent. A report whose data starts with 0x0 is then
+    /// easily sent one byte short. Here the Report ID 0x0 is prepended internally,
+    /// so `data` only contains the report data, like with [`HidDevice::set_report`].
+    /// Do not use this with devices which use numbered reports.
+    ///
+    /// If successful, returns the number of bytes of `data` written.
+    pub fn write_raw(&self, data: &[u8]) -> HidResult<usize> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+
+        let mut report = Vec::with_capacity(data.len() + 1);
+        report.push(0x0);
+        report.extend_from_slice(data);
+
+        match self.inner.write(&report) {
+            // windows-native pads the report to the longest Output report
+            Ok(written) => Ok(written.saturating_sub(1).min(data.len())),
+            Err(HidError::IncompleteSendError { sent, .. }) => Err(HidError::IncompleteSendError {
+                sent: sent.saturating_sub(1).min(data.len()),
+                all: data.len(),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Read an Input report from a HID device.
     ///
     /// Input reports are returned to the host through the 'INTERRUPT IN'
@@ -552,7 +1658,8 @@ impl HidDevice {
     ///
     /// If successful, returns the actual number of bytes read.
     pub fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
-        self.inner.read(buf)
+        check_buffer(buf)?;
+        self.record_read(self.inner.read(buf))
     }
 
     /// Read an Input report from a HID device with timeout.
@@ -564,7 +1671,198 @@ impl HidDevice {
     ///
     /// If successful, returns the actual number of bytes read.
     pub fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
-        self.inner.read_timeout(buf, timeout)
+        check_buffer(buf)?;
+        self.record_read(self.inner.read_timeout(buf, timeout))
+    }
+
+    /// Read an Input report from a HID device into a buffer sized to hold the
+    /// longest Input report of the device.
+    ///
+    /// The size is taken from the report descriptor, which is read once and then
+    /// cached, and includes the Report ID byte for numbered reports. If the
+    /// descriptor can not be read, a buffer of 4096 bytes is used, which is the
+    /// largest report the Linux kernel supports. This fallback does not depend on
+    /// the bus type on purpose: high-speed USB and Bluetooth devices may send
+    /// reports longer than the 64 bytes of a full-speed USB endpoint. The returned
+    /// data is truncated to the bytes read, and is empty in non-blocking mode if
+    /// no report was available.
+    ///
+    /// A new buffer is allocated on every call; use `read()` with a reused buffer
+    /// when polling.
+    pub fn read_vec(&self) -> HidResult<Vec<u8>> {
+        let len = self
+            .report_descriptor()
+            .ok()
+            .and_then(|descriptor| descriptor.max_report_len(ReportType::Input))
+            .unwrap_or(4096);
+
+        let mut buf = vec![0u8; len];
+        let len = self.read(&mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Read an Input report from a HID device, and return its Report ID separately.
+    ///
+    /// `read()` puts the Report ID into `buf[0]` only for devices which use
+    /// numbered reports. Here it is returned as `Some(id)` instead, and `buf` only
+    /// receives the report data, whose length is returned too. For devices which
+    /// do not use numbered reports the Report ID is `None`. Whether a device uses
+    /// them is looked up in its report descriptor, which is read once and cached.
+    /// If the descriptor can not be read, the device is assumed not to use them.
+    /// If no report is available, `(None, 0)` is returned. Numbered reports are
+    /// read into a buffer kept with the device, so repeated reads do not allocate.
+    pub fn read_report(&self, buf: &mut [u8]) -> HidResult<(Option<u8>, usize)> {
+        check_buffer(buf)?;
+        let numbered = self
+            .report_descriptor()
+            .is_ok_and(|descriptor| descriptor.uses_numbered_reports());
+        if !numbered {
+            return Ok((None, self.read(buf)?));
+        }
+
+        // Read with room for the Report ID, which is stripped again
+        let mut report = self.report_buf.borrow_mut();
+        report.resize(buf.len() + 1, 0);
+        let len = self.read(&mut report)?;
+        if len == 0 {
+            return Ok((None, 0));
+        }
+
+        buf[..len - 1].copy_from_slice(&report[1..len]);
+        Ok((Some(report[0]), len - 1))
+    }
+
+    /// Read an Input report from a HID device, waiting until `deadline` at most.
+    ///
+    /// Unlike with `read_timeout()`, the time spent waiting does not drift when
+    /// reading from multiple devices in turn. Returns `Ok(0)` if no report was
+    /// received before the deadline, a deadline in the past only checks for a
+    /// report which is already available.
+    pub fn read_deadline(&self, buf: &mut [u8], deadline: Instant) -> HidResult<usize> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            // Round up, so the deadline is not missed by less than a millisecond
+            let timeout = remaining.as_micros().div_ceil(1000);
+            let read = self.read_timeout(buf, i32::try_from(timeout).unwrap_or(i32::MAX))?;
+
+            // The backend may wake up early without a report
+            if read > 0 || Instant::now() >= deadline {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Get statistics about the reports read with `read()` and `read_timeout()`
+    /// since the device was opened.
+    ///
+    /// This helps telling apart a device which sends nothing from an application
+    /// which does not read its reports.
+    pub fn stats(&self) -> HidStats {
+        self.stats.get()
+    }
+
+    /// Discard all Input reports which are currently queued for the device.
+    ///
+    /// This is useful for devices where only the current state matters (e.g. the
+    /// position of an axis), so the next `read()` does not return stale reports
+    /// which were buffered while the application was not reading.
+    pub fn flush_input(&self) -> HidResult<()> {
+        // Reports which do not fit into the buffer are truncated, but still
+        // removed from the queue
+        let mut buf = [0u8; 64];
+        while self.inner.read_timeout(&mut buf, 0)? > 0 {}
+        Ok(())
+    }
+
+    /// Discard all Input reports which arrive until `duration` after the device
+    /// was opened, and return how many there were.
+    ///
+    /// Some devices send a burst of buffered, stale reports right after being
+    /// opened. Calling this right after opening blocks until `duration` has passed
+    /// since then, so the first `read()` returns fresh data. Discarded reports are
+    /// not counted in [`HidDevice::stats`].
+    pub fn discard_initial_reports(&self, duration: Duration) -> HidResult<usize> {
+        let deadline = self.opened + duration;
+        let mut buf = [0u8; 64];
+        let mut discarded = 0;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(discarded);
+            }
+
+            let timeout = remaining.as_micros().div_ceil(1000);
+            if self
+                .inner
+                .read_timeout(&mut buf, i32::try_from(timeout).unwrap_or(i32::MAX))?
+                > 0
+            {
+                discarded += 1;
+            }
+        }
+    }
+
+    /// Get a handle which can be used to cancel blocking reads from another thread.
+    ///
+    /// Currently this is only supported by the `linux-native` backends, all other
+    /// backends return an error.
+    pub fn read_canceller(&self) -> HidResult<ReadCanceller> {
+        self.inner.read_canceller()
+    }
+
+    /// Check if an Input report can be read without blocking, without consuming it.
+    ///
+    /// This allows event loop integrations to decide whether to call `read()`.
+    /// Currently this is only supported by the `linux-native` backends, all other
+    /// backends return an error.
+    pub fn has_pending_report(&self) -> HidResult<bool> {
+        self.inner.has_pending_report()
+    }
+
+    /// Get the number of Input reports which are queued for reading, e.g. to
+    /// notice that the application reads too slowly and should start to drain.
+    ///
+    /// This is a lower bound: the kernel does not report the length of the hidraw
+    /// queue, so the `linux-native` backends return 1 if a report is pending and 0
+    /// otherwise. The mock backend counts exactly, all other backends return an
+    /// error.
+    pub fn queued_report_count(&self) -> HidResult<usize> {
+        self.inner.queued_report_count()
+    }
+
+    /// Take the file descriptor of the hidraw device node, without closing it.
+    ///
+    /// This allows handing the device to e.g. an async runtime, which then manages
+    /// its lifetime. [`HidApi::open_fd`] turns the fd into a `HidDevice` again.
+    /// Use `into_raw_fd()` on the result to get a `RawFd`.
+    #[cfg(all(
+        any(feature = "linux-native", feature = "linux-native-sysfs"),
+        target_os = "linux"
+    ))]
+    pub fn into_fd(self) -> HidResult<std::os::fd::OwnedFd> {
+        self.inner.into_fd()
+    }
+
+    /// Issue an arbitrary ioctl on the hidraw device node, passing `buf` as the argument.
+    ///
+    /// This is meant for vendor tools which need ioctls that are not wrapped by this
+    /// crate, e.g. feature reports with a non-standard length. The request number can
+    /// be built with [`hidraw_request_code`], which works like the kernel's `_IOC` macro.
+    /// On success the return value of the ioctl is returned.
+    ///
+    /// Currently this is only supported by the `linux-native` backends, all other
+    /// backends return an error.
+    ///
+    /// # Safety
+    ///
+    /// The kernel reads from and writes to `buf` as described by `request`. The caller
+    /// must make sure that the size encoded in `request` is not larger than `buf`, and
+    /// that the ioctl does not expect a pointer to anything other than a byte buffer.
+    #[cfg(feature = "linux-raw-ioctl")]
+    pub unsafe fn ioctl_raw(&self, request: u32, buf: &mut [u8]) -> HidResult<i32> {
+        self.inner.ioctl_raw(request, buf)
     }
 
     /// Send a Feature report to the device.
@@ -585,18 +1883,79 @@ impl HidDevice {
         self.inner.send_feature_report(data)
     }
 
+    /// Send a feature report with the given 'Report ID' to the device.
+    ///
+    /// For devices which do not use numbered reports, `report_id` must be 0x0.
+    /// Unlike [`HidDevice::send_feature_report`], `data` only contains the
+    /// report data, the Report ID is prepended internally.
+    ///
+    /// If the report descriptor declares the report, `data` must have exactly the
+    /// length given by [`HidDevice::feature_report_len`], otherwise an error is
+    /// returned without sending anything. Without a descriptor, or for a report it
+    /// does not declare, `data` is sent as it is.
+    pub fn send_feature_report_id(&self, report_id: u8, data: &[u8]) -> HidResult<()> {
+        if let Some(len) = self.feature_report_len(report_id) {
+            if data.len() != len {
+                return Err(HidError::HidApiError {
+                    message: format!(
+                        "send_feature_report_id: Feature report {report_id} has {len} bytes, got {}",
+                        data.len()
+                    ),
+                });
+            }
+        }
+        self.set_report(ReportType::Feature, report_id, data)
+    }
+
     /// Get a feature report from a HID device.
     ///
     /// Set the first byte of `buf` to the 'Report ID' of the report to be read.
     /// Upon return, the first byte will still contain the Report ID, and the
     /// report data will start in `buf[1]`.
     ///
+    /// Note that `buf[0]` is used both as input (the requested report ID) and as
+    /// part of the output. Use [`HidDevice::get_feature_report_id`] to pass the
+    /// report ID separately and only get the report data back.
+    ///
     /// If successful, returns the number of bytes read plus one for the report ID (which is still
     /// in the first byte).
     pub fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        check_buffer(buf)?;
         self.inner.get_feature_report(buf)
     }
 
+    /// Get a feature report with the given 'Report ID' from a HID device.
+    ///
+    /// For devices which do not use numbered reports, `report_id` must be 0x0.
+    /// Unlike [`HidDevice::get_feature_report`], only the report data (without
+    /// the Report ID) is written to `buf`.
+    ///
+    /// If successful, returns the number of report data bytes written to `buf`.
+    pub fn get_feature_report_id(&self, report_id: u8, buf: &mut [u8]) -> HidResult<usize> {
+        self.get_report(ReportType::Feature, report_id, buf)
+    }
+
+    /// Get the feature report with the given 'Report ID', using a buffer sized
+    /// according to the report descriptor of the device.
+    ///
+    /// For devices which do not use numbered reports, `report_id` must be 0x0.
+    /// The returned data does not include the Report ID. Fails if the
+    /// descriptor does not declare a feature report with this ID.
+    pub fn get_feature_report_full(&self, report_id: u8) -> HidResult<Vec<u8>> {
+        let len = self
+            .feature_report_len(report_id)
+            .ok_or_else(|| HidError::HidApiError {
+                message: format!(
+                    "no feature report with id {report_id:#04x} in the report descriptor"
+                ),
+            })?;
+
+        let mut buf = vec![0u8; len];
+        let len = self.get_report(ReportType::Feature, report_id, &mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
     /// Send a Output report to the device.
     ///
     /// Output reports are sent over the Control endpoint as a Set_Report
@@ -614,6 +1973,65 @@ impl HidDevice {
         self.inner.send_output_report(data)
     }
 
+    /// Send a report of the given type and 'Report ID' as a Set_Report transfer.
+    ///
+    /// For devices which do not use numbered reports, `report_id` must be 0x0.
+    /// `data` only contains the report data, the Report ID is prepended internally.
+    ///
+    /// Output reports are sent like with [`HidDevice::send_output_report`], Feature
+    /// reports like with [`HidDevice::send_feature_report`]. Sending Input reports
+    /// is currently only supported by the `linux-native` backends.
+    pub fn set_report(&self, report_type: ReportType, report_id: u8, data: &[u8]) -> HidResult<()> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+
+        let mut report = Vec::with_capacity(data.len() + 1);
+        report.push(report_id);
+        report.extend_from_slice(data);
+
+        match report_type {
+            ReportType::Input => self.inner.set_input_report(&report),
+            ReportType::Output => self.inner.send_output_report(&report),
+            ReportType::Feature => self.inner.send_feature_report(&report),
+        }
+    }
+
+    /// Get a report of the given type and 'Report ID' with a Get_Report transfer.
+    ///
+    /// For devices which do not use numbered reports, `report_id` must be 0x0.
+    /// Only the report data (without the Report ID) is written to `buf`.
+    ///
+    /// Input reports are read like with [`HidDevice::get_input_report`], which is
+    /// not available on every backend. Getting Output reports is not supported.
+    ///
+    /// If successful, returns the number of report data bytes written to `buf`.
+    pub fn get_report(
+        &self,
+        report_type: ReportType,
+        report_id: u8,
+        buf: &mut [u8],
+    ) -> HidResult<usize> {
+        let mut report = vec![0u8; buf.len() + 1];
+        report[0] = report_id;
+
+        let res = match report_type {
+            #[cfg(any(hidapi, target_os = "linux"))]
+            ReportType::Input => self.inner.get_input_report(&mut report)?,
+            ReportType::Feature => self.inner.get_feature_report(&mut report)?,
+            _ => {
+                return Err(HidError::HidApiError {
+                    message: format!("get_report: {report_type:?} reports are not supported"),
+                })
+            }
+        };
+
+        // All backends count the report ID in the returned size
+        let len = res.saturating_sub(1).min(buf.len());
+        buf[..len].copy_from_slice(&report[1..=len]);
+        Ok(len)
+    }
+
     /// Get a input report from a HID device
     ///
     /// Set the first byte of data to the report id of the report to be read.
@@ -624,6 +2042,7 @@ impl HidDevice {
     /// in the first byte).
     #[cfg(any(hidapi, target_os = "linux"))]
     pub fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize> {
+        check_buffer(data)?;
         self.inner.get_input_report(data)
     }
 
@@ -636,6 +2055,12 @@ impl HidDevice {
         self.inner.set_blocking_mode(blocking)
     }
 
+    /// Check if the device handle is in blocking mode, as set by
+    /// [`HidDevice::set_blocking_mode`]. Devices are opened in blocking mode.
+    pub fn is_blocking(&self) -> bool {
+        self.inner.is_blocking()
+    }
+
     /// Get The Manufacturer String from a HID device.
     pub fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
         self.inner.get_manufacturer_string()
@@ -660,12 +2085,732 @@ impl HidDevice {
     ///
     /// User has to provide a preallocated buffer where the descriptor will be copied to.
     /// It is recommended to use a preallocated buffer of [`MAX_REPORT_DESCRIPTOR_SIZE`] size.
+    /// If the buffer is too small, the descriptor is silently truncated; use
+    /// [`HidDevice::get_report_descriptor_vec`] to avoid that.
     pub fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
         self.inner.get_report_descriptor(buf)
     }
 
+    /// Get the report descriptor from a HID device as an owned buffer.
+    ///
+    /// The returned descriptor has exactly the length reported by the device, up
+    /// to [`MAX_REPORT_DESCRIPTOR_SIZE`].
+    pub fn get_report_descriptor_vec(&self) -> HidResult<Vec<u8>> {
+        let mut buf = vec![0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+        let len = self.get_report_descriptor(&mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Get the length of the Feature report with the given 'Report ID', as declared
+    /// in the report descriptor.
+    ///
+    /// For devices which do not use numbered reports, `report_id` must be 0x0.
+    /// The length does not include the Report ID, so it is the size of the buffer
+    /// needed by [`HidDevice::get_feature_report_id`]. Returns `None` if there is no
+    /// Feature report with this ID, or the report descriptor can not be read.
+    ///
+    /// The descriptor is read the first time it is needed and then cached.
+    pub fn feature_report_len(&self, report_id: u8) -> Option<usize> {
+        self.report_descriptor()
+            .ok()?
+            .report_len(ReportType::Feature, report_id)
+    }
+
+    fn report_descriptor(&self) -> HidResult<Ref<'_, ReportDescriptor>> {
+        if self.descriptor.borrow().is_none() {
+            let descriptor = ReportDescriptor::from_vec(self.get_report_descriptor_vec()?);
+            self.descriptor.replace(Some(descriptor));
+        }
+
+        let descriptor = self.descriptor.borrow();
+        Ok(Ref::map(descriptor, |d| d.as_ref().unwrap()))
+    }
+
+    /// Get all (usage page, usage) pairs of the top level collections of a HID device.
+    ///
+    /// The pairs are parsed from the report descriptor of the device, which allows
+    /// checking whether an opened device supports the collection you need before
+    /// sending reports to it.
+    pub fn usage_pairs(&self) -> HidResult<Vec<(u16, u16)>> {
+        Ok(self.report_descriptor()?.usages().collect())
+    }
+
     /// Get [`DeviceInfo`] from a HID device.
     pub fn get_device_info(&self) -> HidResult<DeviceInfo> {
         self.inner.get_device_info()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A USB device with no strings or usage, for tests to override with the
+    /// fields they care about
+    pub(crate) fn test_info() -> DeviceInfo {
+        DeviceInfo::new(CString::new("/dev/hidraw0").unwrap(), 0x046d, 0xc52b)
+    }
+
+    #[test]
+    fn test_bus_type_from_linux_bustype() {
+        assert_eq!(BusType::Usb, BusType::from_linux_bustype(0x03));
+        assert_eq!(BusType::Bluetooth, BusType::from_linux_bustype(0x05));
+        assert_eq!(BusType::I2c, BusType::from_linux_bustype(0x18));
+        assert_eq!(BusType::Spi, BusType::from_linux_bustype(0x1C));
+        assert_eq!(BusType::Unknown, BusType::from_linux_bustype(0x06));
+    }
+
+    #[test]
+    fn test_bus_type_from_transport_str() {
+        assert_eq!(BusType::Usb, BusType::from_transport_str("USB"));
+        assert_eq!(BusType::Bluetooth, BusType::from_transport_str("Bluetooth"));
+        assert_eq!(
+            BusType::Bluetooth,
+            BusType::from_transport_str("BluetoothLowEnergy")
+        );
+        assert_eq!(BusType::I2c, BusType::from_transport_str("I2C"));
+        assert_eq!(BusType::Spi, BusType::from_transport_str("SPI"));
+        assert_eq!(BusType::Unknown, BusType::from_transport_str("FireWire"));
+    }
+
+    #[test]
+    fn test_device_info_summary() {
+        let info = DeviceInfo {
+            release_number: 0x1211,
+            manufacturer_string: WcharString::String("Logitech".into()),
+            product_string: WcharString::String("USB Receiver".into()),
+            usage_page: 0xff00,
+            usage: 0x0001,
+            interface_number: 2,
+            ..test_info()
+        };
+
+        assert_eq!(
+            "Device 046d:c52b\n  \
+             Manufacturer: Logitech\n  \
+             Product: USB Receiver\n  \
+             Serial number: (none)\n  \
+             Release number: 0x1211\n  \
+             Usage page: 0xff00\n  \
+             Usage: 0x0001\n  \
+             Interface number: 2\n  \
+             Bus type: USB\n  \
+             Path: /dev/hidraw0",
+            info.summary()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_device_info_serde_round_trip() {
+        let info = DeviceInfo {
+            release_number: 0x1211,
+            manufacturer_string: WcharString::String("Logitech".into()),
+            product_string: WcharString::Raw(
+                "USB Receiver".chars().map(|c| c as wchar_t).collect(),
+            ),
+            usage_page: 0xff00,
+            usage: 0x0001,
+            interface_number: 2,
+            ..test_info()
+        };
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!("/dev/hidraw0", json["path"]);
+        assert_eq!("USB Receiver", json["product_string"]);
+        assert!(json["serial_number"].is_null());
+
+        let info: DeviceInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            info.path(),
+            CString::new("/dev/hidraw0").unwrap().as_c_str()
+        );
+        assert_eq!((0x046d, 0xc52b), (info.vendor_id(), info.product_id()));
+        assert_eq!(None, info.serial_number());
+        assert_eq!(Some("Logitech"), info.manufacturer_string());
+        assert_eq!(Some("USB Receiver"), info.product_string());
+        assert_eq!(BusType::Usb, info.bus_type());
+    }
+
+    #[test]
+    fn test_device_id_matches() {
+        let info = DeviceInfo {
+            path: CString::new("/dev/hidraw3").unwrap(),
+            serial_number: WcharString::String("1234".into()),
+            release_number: 0x1211,
+            usage_page: 0xff00,
+            usage: 0x0001,
+            interface_number: 2,
+            ..test_info()
+        };
+        let id = info.id();
+        assert!(id.matches(&info));
+
+        let other_serial = DeviceInfo {
+            serial_number: WcharString::String("5678".into()),
+            ..info.clone()
+        };
+        assert!(!id.matches(&other_serial));
+
+        let other_usage = DeviceInfo {
+            usage: 0x0002,
+            ..info.clone()
+        };
+        assert!(!id.matches(&other_usage));
+
+        let other_path = DeviceInfo {
+            path: CString::new("/dev/hidraw4").unwrap(),
+            ..info
+        };
+        assert!(id.matches(&other_path));
+    }
+
+    #[test]
+    fn test_group_devices() {
+        let device = |pid: u16, serial: &str, interface_number: i32| DeviceInfo {
+            path: CString::new(format!("/dev/hidraw{interface_number}")).unwrap(),
+            product_id: pid,
+            serial_number: WcharString::String(serial.into()),
+            interface_number,
+            ..test_info()
+        };
+        let devices = [
+            device(0xc24f, "A", 0),
+            device(0xc52b, "B", 0),
+            device(0xc24f, "A", 1),
+            device(0xc24f, "C", 0),
+            device(0xc24f, "A", 2),
+        ];
+
+        let groups = group_devices(devices.iter());
+        let summary: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                (
+                    group.product_id(),
+                    group.serial_number().unwrap(),
+                    group
+                        .devices()
+                        .iter()
+                        .map(|info| info.interface_number())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (0xc24f, "A", vec![0, 1, 2]),
+                (0xc52b, "B", vec![0]),
+                (0xc24f, "C", vec![0]),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_lossy_strings() {
+        // An unpaired surrogate is invalid in both UTF-16 and UTF-32
+        let raw: Vec<wchar_t> = vec![0x41, 0xd800, 0x42];
+        let info = DeviceInfo {
+            serial_number: WcharString::Raw(raw.clone()),
+            manufacturer_string: WcharString::String("Logitech".into()),
+            ..test_info()
+        };
+
+        assert_eq!(None, info.serial_number());
+        assert_eq!(Some(&raw[..]), info.serial_number_raw());
+        assert_eq!(Some("A\u{fffd}B".to_string()), info.serial_number_lossy());
+        assert_eq!(
+            Some("Logitech".to_string()),
+            info.manufacturer_string_lossy()
+        );
+        assert_eq!(None, info.product_string_lossy());
+    }
+
+    #[test]
+    fn test_redacted_device_info() {
+        let info = DeviceInfo {
+            serial_number: WcharString::String("AB12345678".into()),
+            ..test_info()
+        };
+
+        assert!(info
+            .redacted()
+            .to_string()
+            .contains("Serial number: ******5678\n"));
+        assert!(!info.redacted().to_string().contains("AB12345678"));
+        assert_eq!(
+            "HidDeviceInfo { vendor_id: 1133, product_id: 50475, serial_number: \"******5678\" }",
+            format!("{:?}", info.redacted())
+        );
+
+        let short = DeviceInfo {
+            serial_number: WcharString::String("123".into()),
+            ..info
+        };
+        assert!(short
+            .redacted()
+            .to_string()
+            .contains("Serial number: **3\n"));
+        let single = DeviceInfo {
+            serial_number: WcharString::String("1".into()),
+            ..test_info()
+        };
+        assert!(single.redacted().to_string().contains("Serial number: *\n"));
+    }
+
+    #[test]
+    fn test_device_list_delta() {
+        let device = |path: &str, usage: u16| DeviceInfo {
+            path: CString::new(path).unwrap(),
+            usage_page: 0x01,
+            usage,
+            ..test_info()
+        };
+        let previous = [
+            device("/dev/hidraw0", 0x02),
+            device("/dev/hidraw0", 0x06),
+            device("/dev/hidraw1", 0x02),
+        ];
+        let current = [
+            device("/dev/hidraw0", 0x02),
+            device("/dev/hidraw0", 0x06),
+            device("/dev/hidraw2", 0x02),
+        ];
+
+        let (added, removed) = device_list_delta(&previous, &current);
+        let paths = |list: Vec<DeviceInfo>| -> Vec<_> {
+            list.iter()
+                .map(|info| info.path().to_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(vec!["/dev/hidraw2"], paths(added));
+        assert_eq!(vec!["/dev/hidraw1"], paths(removed));
+
+        let (added, removed) = device_list_delta(&current, &current);
+        assert!(added.is_empty() && removed.is_empty());
+    }
+
+    #[test]
+    fn test_device_info_identity() {
+        let device = |path: &str, usage: u16, release_number: u16| DeviceInfo {
+            path: CString::new(path).unwrap(),
+            serial_number: WcharString::String("A".into()),
+            release_number,
+            usage_page: 0x01,
+            usage,
+            ..test_info()
+        };
+
+        let mouse = device("/dev/hidraw0", 0x02, 0x0100);
+        assert_eq!(mouse, device("/dev/hidraw0", 0x02, 0x0200));
+        assert_ne!(mouse, device("/dev/hidraw0", 0x06, 0x0100));
+        assert_ne!(mouse, device("/dev/hidraw1", 0x02, 0x0100));
+        assert!(mouse.same_device(&device("/dev/hidraw0", 0x06, 0x0100)));
+        assert!(!mouse.same_device(&device("/dev/hidraw1", 0x02, 0x0100)));
+
+        let set: std::collections::HashSet<_> = [
+            mouse.clone(),
+            device("/dev/hidraw0", 0x02, 0x0200),
+            device("/dev/hidraw0", 0x06, 0x0100),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(2, set.len());
+        assert!(set.contains(&mouse));
+    }
+
+    #[test]
+    fn test_bus_type_display() {
+        assert_eq!("Unknown", BusType::Unknown.to_string());
+        assert_eq!("USB", BusType::Usb.to_string());
+        assert_eq!("Bluetooth", BusType::Bluetooth.to_string());
+        assert_eq!("I2C", BusType::I2c.to_string());
+        assert_eq!("SPI", BusType::Spi.to_string());
+    }
+
+    /// The `HidApi` and `HidDevice` logic on top of the backends, tested with
+    /// the mock backend
+    #[cfg(feature = "mock")]
+    mod mocked {
+        use super::*;
+        use crate::mock::MockDevice;
+
+        #[test]
+        fn test_open_filtered() {
+            let keyboard = MockDevice::new(0x046d, 0xc52b)
+                .serial_number("A")
+                .usage(0x01, 0x06);
+            let mouse = MockDevice::new(0x046d, 0xc52b)
+                .serial_number("A")
+                .usage(0x01, 0x02);
+            let other = MockDevice::new(0x046d, 0xc52b)
+                .serial_number("B")
+                .usage(0x0c, 0x01);
+            let api = HidApi::new_mocked(vec![keyboard, mouse, other]);
+
+            let open = |serial, usage_page, usage| {
+                let device = api.open_filtered(0x046d, 0xc52b, serial, usage_page, usage)?;
+                let info = device.get_device_info()?;
+                Ok::<_, HidError>((info.serial_number().map(str::to_owned), info.usage()))
+            };
+            assert_eq!((Some("A".into()), 0x06), open(None, None, None).unwrap());
+            assert_eq!(
+                (Some("A".into()), 0x02),
+                open(Some("A"), Some(0x01), Some(0x02)).unwrap()
+            );
+            assert_eq!(
+                (Some("B".into()), 0x01),
+                open(None, Some(0x0c), None).unwrap()
+            );
+            assert_eq!(
+                (Some("B".into()), 0x01),
+                open(Some("B"), None, None).unwrap()
+            );
+            let error = open(Some("B"), Some(0x01), None).unwrap_err();
+            assert_eq!(
+                "No hid device 046d:c52b with serial number \"B\" with usage page 0x0001 found",
+                error.to_string()
+            );
+            assert!(matches!(
+                error,
+                HidError::DeviceNotFound {
+                    serial_number: Some(_),
+                    usage_page: Some(0x01),
+                    usage: None,
+                    ..
+                }
+            ));
+        }
+
+        #[test]
+        fn test_open_unique() {
+            let keyboard = MockDevice::new(0x046d, 0xc52b).usage(0x01, 0x06);
+            let mouse = MockDevice::new(0x046d, 0xc52b).usage(0x01, 0x02);
+            let api = HidApi::new_mocked(vec![keyboard.clone(), mouse]);
+            assert!(api.open_unique(0x046d, 0xc52b).is_ok());
+            assert!(matches!(
+                api.open_unique(0x046d, 0xc24f),
+                Err(HidError::DeviceNotFound { .. })
+            ));
+
+            // Identical devices, with and without serial numbers
+            let api = HidApi::new_mocked(vec![keyboard.clone(), keyboard.clone()]);
+            assert!(api.open_unique(0x046d, 0xc52b).is_err());
+            let api = HidApi::new_mocked(vec![
+                keyboard.clone().serial_number("A"),
+                keyboard.serial_number("B"),
+            ]);
+            assert!(api.open_unique(0x046d, 0xc52b).is_err());
+            assert!(api.open_serial(0x046d, 0xc52b, "B").is_ok());
+        }
+
+        #[test]
+        fn test_read_deadline() {
+            let mock = MockDevice::new(0x046d, 0xc52b);
+            let api = HidApi::new_mocked(vec![mock.clone()]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+            let mut buf = [0u8; 8];
+
+            let deadline = Instant::now() + Duration::from_millis(20);
+            assert_eq!(0, device.read_deadline(&mut buf, deadline).unwrap());
+            assert!(Instant::now() >= deadline);
+
+            mock.queue_input_report(&[0x01, 0x02]);
+            // Already passed once read_deadline checks it
+            let deadline = Instant::now();
+            assert_eq!(2, device.read_deadline(&mut buf, deadline).unwrap());
+        }
+
+        #[test]
+        fn test_open_all() {
+            let api = HidApi::new_mocked(vec![
+                MockDevice::new(0x046d, 0xc52b).usage(0x01, 0x06),
+                MockDevice::new(0x046d, 0xc52b).usage(0x01, 0x02),
+                MockDevice::new(0x046d, 0xc24f),
+                MockDevice::new(0x1234, 0x5678),
+            ]);
+
+            assert_eq!(3, api.open_all(Some(0x046d), None).len());
+            assert_eq!(4, api.open_all(None, None).len());
+            let opened = api.open_all(Some(0x046d), Some(0xc52b));
+            assert_eq!(2, opened.len());
+            for (info, device) in opened {
+                assert_eq!(
+                    info.usage(),
+                    device.unwrap().get_device_info().unwrap().usage()
+                );
+            }
+        }
+
+        #[test]
+        fn test_open_usage() {
+            let keyboard = MockDevice::new(0x046d, 0xc52b).usage(0x01, 0x06);
+            let mouse = MockDevice::new(0x046d, 0xc52b).usage(0x01, 0x02);
+            let api = HidApi::new_mocked(vec![keyboard, mouse]);
+
+            let device = api.open_usage(0x046d, 0xc52b, 0x01, 0x02).unwrap();
+            let info = device.get_device_info().unwrap();
+            assert_eq!((0x01, 0x02), (info.usage_page(), info.usage()));
+
+            assert!(matches!(
+                api.open_usage(0x046d, 0xc52b, 0x0c, 0x01),
+                Err(HidError::DeviceNotFound { .. })
+            ));
+        }
+
+        #[test]
+        fn test_discard_initial_reports() {
+            let mock = MockDevice::new(0x046d, 0xc52b);
+            let api = HidApi::new_mocked(vec![mock.clone()]);
+            mock.queue_input_report(&[0x01]);
+            mock.queue_input_report(&[0x02]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+
+            let start = Instant::now();
+            assert_eq!(
+                2,
+                device
+                    .discard_initial_reports(Duration::from_millis(20))
+                    .unwrap()
+            );
+            assert!(start.elapsed() >= Duration::from_millis(10));
+            assert_eq!(0, device.stats().reports_received);
+
+            // Later calls return right away
+            assert_eq!(
+                0,
+                device
+                    .discard_initial_reports(Duration::from_millis(20))
+                    .unwrap()
+            );
+
+            mock.queue_input_report(&[0x03]);
+            assert_eq!(vec![0x03], device.read_vec().unwrap());
+        }
+
+        #[test]
+        fn test_read_report() {
+            #[rustfmt::skip]
+            let descriptor = [
+                0x85, 0x01,       // Report ID (1)
+                0x75, 0x08,       // Report Size (8)
+                0x95, 0x02,       // Report Count (2)
+                0x81, 0x02,       // Input (Data, Variable, Absolute)
+            ];
+            let numbered = MockDevice::new(0x046d, 0xc52b).report_descriptor(&descriptor);
+            let unnumbered = MockDevice::new(0x1234, 0x5678).report_descriptor(&descriptor[2..]);
+            let api = HidApi::new_mocked(vec![numbered.clone(), unnumbered.clone()]);
+
+            let device = api.open(0x046d, 0xc52b).unwrap();
+            device.set_blocking_mode(false).unwrap();
+            let mut buf = [0u8; 2];
+            assert_eq!((None, 0), device.read_report(&mut buf).unwrap());
+            numbered.queue_input_report(&[0x01, 0xaa, 0xbb]);
+            assert_eq!((Some(0x01), 2), device.read_report(&mut buf).unwrap());
+            assert_eq!([0xaa, 0xbb], buf);
+
+            let device = api.open(0x1234, 0x5678).unwrap();
+            unnumbered.queue_input_report(&[0x01, 0xaa]);
+            assert_eq!((None, 2), device.read_report(&mut buf).unwrap());
+            assert_eq!([0x01, 0xaa], buf);
+
+            // Without a descriptor the first byte is data as well
+            let mock = MockDevice::new(0x046d, 0xc24f);
+            let api = HidApi::new_mocked(vec![mock.clone()]);
+            let device = api.open(0x046d, 0xc24f).unwrap();
+            mock.queue_input_report(&[0x01, 0xaa]);
+            assert_eq!((None, 2), device.read_report(&mut buf).unwrap());
+            assert_eq!([0x01, 0xaa], buf);
+        }
+
+        #[test]
+        fn test_read_vec() {
+            #[rustfmt::skip]
+            let descriptor = [
+                0x85, 0x01,       // Report ID (1)
+                0x75, 0x08,       // Report Size (8)
+                0x95, 0x02,       // Report Count (2)
+                0x81, 0x02,       // Input (Data, Variable, Absolute)
+                0x85, 0x02,       // Report ID (2)
+                0x95, 0x05,       // Report Count (5)
+                0x81, 0x02,       // Input (Data, Variable, Absolute)
+            ];
+            let mock = MockDevice::new(0x046d, 0xc52b).report_descriptor(&descriptor);
+            let api = HidApi::new_mocked(vec![mock.clone()]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+
+            mock.queue_input_report(&[0x02, 1, 2, 3, 4, 5, 6, 7]);
+            mock.queue_input_report(&[0x01, 1, 2]);
+            // Truncated to the longest report the descriptor declares
+            assert_eq!(vec![0x02, 1, 2, 3, 4, 5], device.read_vec().unwrap());
+            assert_eq!(vec![0x01, 1, 2], device.read_vec().unwrap());
+        }
+
+        #[test]
+        fn test_get_feature_report_full() {
+            #[rustfmt::skip]
+            let descriptor = [
+                0x85, 0x05,       // Report ID (5)
+                0x75, 0x08,       // Report Size (8)
+                0x95, 0x03,       // Report Count (3)
+                0xb1, 0x02,       // Feature (Data, Variable, Absolute)
+            ];
+            let mock = MockDevice::new(0x046d, 0xc52b)
+                .report_descriptor(&descriptor)
+                .feature_report(&[0x05, 0x01, 0x02, 0x03, 0x04]);
+            let api = HidApi::new_mocked(vec![mock]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+
+            // Only as much as the descriptor declares is read
+            assert_eq!(
+                vec![0x01, 0x02, 0x03],
+                device.get_feature_report_full(0x05).unwrap()
+            );
+            assert!(device.get_feature_report_full(0x06).is_err());
+            assert_eq!(Some(3), device.feature_report_len(0x05));
+            assert_eq!(None, device.feature_report_len(0x06));
+        }
+
+        #[test]
+        fn test_send_feature_report_id() {
+            #[rustfmt::skip]
+            let descriptor = [
+                0x85, 0x05,       // Report ID (5)
+                0x75, 0x08,       // Report Size (8)
+                0x95, 0x03,       // Report Count (3)
+                0xb1, 0x02,       // Feature (Data, Variable, Absolute)
+            ];
+            let mock = MockDevice::new(0x046d, 0xc52b).report_descriptor(&descriptor);
+            let api = HidApi::new_mocked(vec![mock]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+
+            assert!(device
+                .send_feature_report_id(0x05, &[0x01, 0x02, 0x03, 0x04])
+                .is_err());
+            assert!(device.send_feature_report_id(0x05, &[0x01, 0x02]).is_err());
+            assert!(device.get_feature_report_full(0x05).is_err());
+
+            device
+                .send_feature_report_id(0x05, &[0x01, 0x02, 0x03])
+                .unwrap();
+            assert_eq!(
+                vec![0x01, 0x02, 0x03],
+                device.get_feature_report_full(0x05).unwrap()
+            );
+            // Reports the descriptor does not declare are not checked
+            device.send_feature_report_id(0x06, &[0x01]).unwrap();
+        }
+
+        #[test]
+        fn test_queued_report_count() {
+            let mock = MockDevice::new(0x046d, 0xc52b);
+            let api = HidApi::new_mocked(vec![mock.clone()]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+            assert_eq!(0, device.queued_report_count().unwrap());
+
+            mock.queue_input_report(&[1, 2]);
+            mock.queue_input_report(&[3, 4]);
+            assert_eq!(2, device.queued_report_count().unwrap());
+
+            let mut buf = [0u8; 2];
+            device.read(&mut buf).unwrap();
+            assert_eq!(1, device.queued_report_count().unwrap());
+        }
+
+        #[test]
+        fn test_capabilities() {
+            let api = HidApi::new_mocked(vec![MockDevice::new(0x046d, 0xc52b)]);
+            let capabilities = api.capabilities();
+            assert!(capabilities.supports_feature_reports);
+            assert!(!capabilities.supports_hotplug);
+            assert!(!capabilities.supports_control_transfer);
+
+            let device = api.open(0x046d, 0xc52b).unwrap();
+            assert!(!capabilities.supports_read_canceller);
+            assert!(device.read_canceller().is_err());
+        }
+
+        #[test]
+        fn test_wait_for_device() {
+            let mut api = HidApi::new_mocked(vec![MockDevice::new(0x046d, 0xc52b)]);
+            let timeout = Some(Duration::from_millis(20));
+
+            let info = api.wait_for_device(0x046d, 0xc52b, timeout).unwrap();
+            assert_eq!(0xc52b, info.product_id());
+            assert!(matches!(
+                api.wait_for_device(0x046d, 0xc24f, timeout),
+                Err(HidError::Timeout)
+            ));
+        }
+
+        #[test]
+        fn test_open_timeout() {
+            let mock = MockDevice::new(0x046d, 0xc52b).serial_number("A");
+            let api = HidApi::new_mocked(vec![mock]);
+            let timeout = Some(Duration::from_millis(100));
+
+            assert!(api.open_timeout(0x046d, 0xc52b, None, timeout).is_ok());
+            assert!(api.open_timeout(0x046d, 0xc52b, Some("A"), None).is_ok());
+            assert!(matches!(
+                api.open_timeout(0x046d, 0xc52b, Some("B"), timeout),
+                Err(HidError::DeviceNotFound { .. })
+            ));
+            assert!(matches!(
+                api.open_timeout(0x046d, 0xc52b, None, Some(Duration::ZERO)),
+                Err(HidError::Timeout)
+            ));
+        }
+
+        #[test]
+        fn test_device_list_with_descriptors() {
+            let api = HidApi::new_mocked(vec![
+                MockDevice::new(0x046d, 0xc52b).report_descriptor(&[0x05, 0x01]),
+                MockDevice::new(0x1234, 0x5678),
+            ]);
+
+            let descriptors: Vec<_> = api
+                .device_list_with_descriptors()
+                .into_iter()
+                .map(|(info, descriptor)| (info.vendor_id(), descriptor))
+                .collect();
+            assert_eq!(
+                vec![(0x046d, vec![0x05, 0x01]), (0x1234, vec![])],
+                descriptors
+            );
+        }
+
+        #[test]
+        fn test_enumerate_shared() {
+            let api = HidApi::new_mocked(vec![
+                MockDevice::new(0x046d, 0xc52b),
+                MockDevice::new(0x046d, 0xc24f),
+                MockDevice::new(0x1234, 0x5678),
+            ]);
+
+            std::thread::scope(|s| {
+                let all = s.spawn(|| api.enumerate(0, 0).unwrap().len());
+                let logitech = s.spawn(|| api.enumerate(0x046d, 0).unwrap().len());
+                assert_eq!(3, all.join().unwrap());
+                assert_eq!(2, logitech.join().unwrap());
+            });
+            assert_eq!(1, api.enumerate(0x046d, 0xc24f).unwrap().len());
+        }
+
+        #[test]
+        fn test_empty_buffers() {
+            let api = HidApi::new_mocked(vec![MockDevice::new(0x046d, 0xc52b)]);
+            let device = api.open(0x046d, 0xc52b).unwrap();
+
+            assert!(matches!(
+                device.read(&mut []),
+                Err(HidError::InvalidZeroSizeData)
+            ));
+            assert!(matches!(
+                device.get_feature_report(&mut []),
+                Err(HidError::InvalidZeroSizeData)
+            ));
+        }
+    }
+}
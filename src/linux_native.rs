@@ -1,80 +1,112 @@
 //! This backend uses libudev to discover devices and then talks to hidraw directly
+//!
+//! With the `linux-native-sysfs` feature the devices are discovered by reading
+//! sysfs instead, so libudev is not needed at all.
 
 mod ioctl;
+#[cfg(feature = "linux-native-sysfs")]
+mod sysfs;
+
+#[cfg(feature = "linux-native-sysfs")]
+use sysfs as udev;
 
 use std::{
     cell::{Cell, Ref, RefCell},
     ffi::{CStr, CString, OsStr, OsString},
     fs::{File, OpenOptions},
-    io::{Cursor, Read, Seek, SeekFrom},
+    io::{self, Read},
     os::{
-        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
-        unix::{ffi::OsStringExt, fs::OpenOptionsExt},
+        fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+        unix::{
+            ffi::{OsStrExt, OsStringExt},
+            fs::OpenOptionsExt,
+        },
     },
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 
 use nix::{
     errno::Errno,
     poll::{poll, PollFd, PollFlags},
-    sys::stat::{fstat, major, minor},
+    sys::stat::{fstat, major, minor, stat},
     unistd::{read, write},
 };
 
-use super::{BusType, DeviceInfo, HidDeviceBackendBase, HidError, HidResult, WcharString};
+use super::{
+    check_deadline, BusType, Capabilities, DeviceInfo, HidDeviceBackendBase, HidError, HidResult,
+    ReadCanceller, WcharString,
+};
+use crate::report_descriptor::ReportDescriptor;
+#[cfg(feature = "linux-raw-ioctl")]
+pub use ioctl::hidraw_request_code;
 use ioctl::{
-    hidraw_ioc_get_feature, hidraw_ioc_get_input, hidraw_ioc_grdescsize, hidraw_ioc_set_feature,
-    hidraw_ioc_set_output,
+    hidraw_ioc_get_feature, hidraw_ioc_get_input, hidraw_ioc_grawinfo, hidraw_ioc_grawname,
+    hidraw_ioc_grawuniq, hidraw_ioc_grdesc, hidraw_ioc_grdescsize, hidraw_ioc_set_feature,
+    hidraw_ioc_set_input, hidraw_ioc_set_output, HidrawDevinfo, HidrawReportDescriptor,
 };
 
-// Bus values from linux/input.h
-const BUS_USB: u16 = 0x03;
-const BUS_BLUETOOTH: u16 = 0x05;
-const BUS_I2C: u16 = 0x18;
-const BUS_SPI: u16 = 0x1C;
-
 pub struct HidApiBackend;
 
 impl HidApiBackend {
-    pub fn get_hid_device_info_vector(vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
-        // The C version assumes these can't fail, and they should only fail in case
-        // of memory allocation issues, at which point maybe we should panic
-        let mut enumerator = match udev::Enumerator::new() {
-            Ok(e) => e,
-            Err(_) => return Ok(Vec::new()),
-        };
-        enumerator.match_subsystem("hidraw").unwrap();
-        let scan = match enumerator.scan_devices() {
-            Ok(s) => s,
-            Err(_) => return Ok(Vec::new()),
-        };
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        supports_feature_reports: true,
+        supports_get_input_report: true,
+        supports_set_input_report: true,
+        supports_report_descriptor: true,
+        supports_read_canceller: true,
+        supports_pending_report_check: true,
+        supports_indexed_strings: false,
+        supports_hotplug: false,
+        supports_control_transfer: false,
+    };
 
-        let devices = scan
-            .filter_map(|device| device_to_hid_device_info(&device))
-            .flatten()
-            .filter(|device| vid == 0 || device.vendor_id == vid)
-            .filter(|device| pid == 0 || device.product_id == pid)
-            .collect::<Vec<_>>();
+    /// HIDRAW_BUFFER_SIZE of the kernel
+    pub const INPUT_QUEUE_SIZE: usize = 64;
 
-        Ok(devices)
+    pub fn get_hid_device_info_vector(vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
+        enumerate(vid, pid, None)
     }
 
     pub fn open(vid: u16, pid: u16) -> HidResult<HidDevice> {
-        HidDevice::open(vid, pid, None)
+        HidDevice::open(vid, pid, None, None)
     }
 
     pub fn open_serial(vid: u16, pid: u16, sn: &str) -> HidResult<HidDevice> {
-        HidDevice::open(vid, pid, Some(sn))
+        HidDevice::open(vid, pid, Some(sn), None)
+    }
+
+    pub fn open_deadline(
+        vid: u16,
+        pid: u16,
+        sn: Option<&str>,
+        deadline: Option<Instant>,
+    ) -> HidResult<HidDevice> {
+        HidDevice::open(vid, pid, sn, deadline)
     }
 
     pub fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
         HidDevice::open_path(device_path)
     }
+
+    pub fn open_path_with_flags(device_path: &CStr, blocking: bool) -> HidResult<HidDevice> {
+        HidDevice::open_path_with_flags(device_path, blocking)
+    }
+
+    pub fn open_fd(fd: OwnedFd) -> HidResult<HidDevice> {
+        HidDevice::from_fd(fd)
+    }
+
+    /// Read the report descriptor of a device from sysfs, without opening it
+    pub fn get_report_descriptor(device_path: &CStr) -> HidResult<Vec<u8>> {
+        let devnum = stat(OsStr::from_bytes(device_path.to_bytes()))?.st_rdev;
+        let descriptor = report_descriptor_from_syspath(&char_device_syspath(devnum))?;
+        Ok(descriptor.as_ref().to_vec())
+    }
 }
 
 fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo>> {
-    let mut infos = Vec::new();
-
     // We're given the hidraw device, but we actually want to go and check out
     // the info for the parent hid device.
     let device = match raw_device.parent_with_subsystem("hid") {
@@ -90,12 +122,9 @@ fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo
         Some(t) => t,
         None => return None,
     };
-    let bus_type = match bus {
-        BUS_USB => BusType::Usb,
-        BUS_BLUETOOTH => BusType::Bluetooth,
-        BUS_I2C => BusType::I2c,
-        BUS_SPI => BusType::Spi,
-        _ => return None,
+    let bus_type = match BusType::from_linux_bustype(bus.into()) {
+        BusType::Unknown => return None,
+        bus_type => bus_type,
     };
     let name = match device.property_value("HID_NAME") {
         Some(name) => name,
@@ -123,10 +152,12 @@ fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo
         release_number: 0,
         manufacturer_string: WcharString::None,
         product_string: WcharString::None,
+        raw_name: osstring_to_string(name.into()),
         usage_page: 0,
         usage: 0,
         interface_number: -1,
         bus_type,
+        country_code: None,
     };
 
     // USB has a bunch more information but everything else gets the same empty
@@ -140,33 +171,31 @@ fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo
         },
     };
 
-    if let Ok(descriptor) = HidrawReportDescriptor::from_syspath(raw_device.syspath()) {
-        let mut usages = descriptor.usages();
+    // A descriptor without usages must not make the device disappear from the
+    // enumeration, so fall back to the primary info in that case
+    let usages = report_descriptor_from_syspath(raw_device.syspath())
+        .map(|descriptor| descriptor.usages().collect())
+        .unwrap_or_default();
 
-        // Get the first usage page and usage for our current DeviceInfo
-        if let Some((usage_page, usage)) = usages.next() {
-            infos.push(DeviceInfo {
-                usage_page,
-                usage,
-                ..info
-            });
-
-            // Now we can create DeviceInfo for all the other usages
-            for (usage_page, usage) in usages {
-                let prev = infos.last().unwrap();
+    Some(expand_usages(info, usages))
+}
 
-                infos.push(DeviceInfo {
-                    usage_page,
-                    usage,
-                    ..prev.clone()
-                })
-            }
-        }
-    } else {
-        infos.push(info);
+/// Create a [`DeviceInfo`] for each of the given usage pairs.
+///
+/// If there are no usage pairs, the info is returned as is.
+fn expand_usages(info: DeviceInfo, usages: Vec<(u16, u16)>) -> Vec<DeviceInfo> {
+    if usages.is_empty() {
+        return vec![info];
     }
 
-    Some(infos)
+    usages
+        .into_iter()
+        .map(|(usage_page, usage)| DeviceInfo {
+            usage_page,
+            usage,
+            ..info.clone()
+        })
+        .collect()
 }
 
 /// Fill in the extra information that's available for a USB device.
@@ -181,8 +210,13 @@ fn fill_in_usb(device: &udev::Device, info: DeviceInfo, name: &OsStr) -> DeviceI
             }
         }
     };
+    // Not every USB device has string descriptors. A missing manufacturer stays
+    // None, the product falls back to the kernel name like for the other buses
     let manufacturer_string = attribute_as_wchar(&usb_dev, "manufacturer");
-    let product_string = attribute_as_wchar(&usb_dev, "product");
+    let product_string = match attribute_as_wchar(&usb_dev, "product") {
+        WcharString::None => osstring_to_string(name.into()),
+        s => s,
+    };
     let release_number = attribute_as_u16(&usb_dev, "bcdDevice").unwrap_or(0);
     let interface_number = device
         .parent_with_subsystem_devtype("usb", "usb_interface")
@@ -190,177 +224,67 @@ fn fill_in_usb(device: &udev::Device, info: DeviceInfo, name: &OsStr) -> DeviceI
         .flatten()
         .and_then(|ref dev| attribute_as_i32(dev, "bInterfaceNumber"))
         .unwrap_or(-1);
+    // The attribute is binary, so it can't be read with attribute_value()
+    let country_code = std::fs::read(usb_dev.syspath().join("descriptors"))
+        .ok()
+        .and_then(|descriptors| hid_country_code(&descriptors, interface_number));
 
     DeviceInfo {
         release_number,
         manufacturer_string,
         product_string,
         interface_number,
+        country_code,
         ..info
     }
 }
 
-#[derive(Default)]
-struct HidrawReportDescriptor(Vec<u8>);
-
-impl HidrawReportDescriptor {
-    /// Open and parse given the "base" sysfs of the device
-    pub fn from_syspath(syspath: &Path) -> HidResult<Self> {
-        let path = syspath.join("device/report_descriptor");
-        let mut f = File::open(path)?;
-        let mut buf = Vec::new();
-        f.read_to_end(&mut buf)?;
-
-        Ok(HidrawReportDescriptor(buf))
-    }
-
-    /// Create a descriptor from a slice
-    ///
-    /// It returns an error if the value slice is too large for it to be a HID
-    /// descriptor
-    #[cfg_attr(not(test), allow(dead_code))]
-    pub fn from_slice(value: &[u8]) -> HidResult<Self> {
-        Ok(HidrawReportDescriptor(value.to_vec()))
-    }
-
-    pub fn usages(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
-        UsageIterator {
-            usage_page: 0,
-            cursor: Cursor::new(&self.0),
-        }
-    }
-}
-
-/// Iterates over the values in a HidrawReportDescriptor
-struct UsageIterator<'a> {
-    usage_page: u16,
-    cursor: Cursor<&'a Vec<u8>>,
-}
-
-impl<'a> Iterator for UsageIterator<'a> {
-    type Item = (u16, u16);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let (usage_page, page) = match next_hid_usage(&mut self.cursor, self.usage_page) {
-            Some(n) => n,
-            None => return None,
-        };
-
-        self.usage_page = usage_page;
-        Some((usage_page, page))
-    }
-}
-
-// This comes from hidapi which apparently comes from Apple's implementation of
-// this
-fn next_hid_usage(cursor: &mut Cursor<&Vec<u8>>, mut usage_page: u16) -> Option<(u16, u16)> {
-    let mut usage = None;
-    let mut usage_pair = None;
-    let initial = cursor.position() == 0;
-
-    while let Some(Ok(key)) = cursor.bytes().next() {
-        // The amount to skip is calculated based off of the start of the
-        // iteration so we need to keep track of that.
-        let position = cursor.position() - 1;
-        let key_cmd = key & 0xfc;
-
-        let (data_len, key_size) = match hid_item_size(key, cursor) {
-            Some(v) => v,
-            None => return None,
-        };
-
-        match key_cmd {
-            // Usage Page 6.2.2.7 (Global)
-            0x4 => {
-                usage_page = match hid_report_bytes(cursor, data_len) {
-                    Ok(v) => v as u16,
-                    Err(_) => break,
-                }
-            }
-            // Usage 6.2.2.8 (Local)
-            0x8 => {
-                usage = match hid_report_bytes(cursor, data_len) {
-                    Ok(v) => Some(v as u16),
-                    Err(_) => break,
-                }
-            }
-            // Collection 6.2.2.4 (Main)
-            0xa0 => {
-                // Usage is a Local Item, unset it
-                if let Some(u) = usage.take() {
-                    usage_pair = Some((usage_page, u))
-                }
-            }
-            // Input 6.2.2.4 (Main)
-		        0x80 |
-            // Output 6.2.2.4 (Main)
-		        0x90 |
-            // Feature 6.2.2.4 (Main)
-		        0xb0 |
-            // End Collection 6.2.2.4 (Main)
-	    0xc0  =>  {
-		// Usage is a Local Item, unset it
-                usage.take();
-            }
-            _ => {}
-        }
-
-        if cursor
-            .seek(SeekFrom::Start(position + (data_len + key_size) as u64))
-            .is_err()
-        {
+/// Find bCountryCode in the HID descriptor of the given interface, in the raw
+/// USB descriptors of the device
+fn hid_country_code(descriptors: &[u8], interface_number: i32) -> Option<u8> {
+    // Interface descriptor, and HID descriptor (7.1 of the HID specification)
+    const INTERFACE: u8 = 0x04;
+    const HID: u8 = 0x21;
+
+    let mut in_interface = false;
+    let mut rest = descriptors;
+    while let [len, kind, ..] = *rest {
+        let len = usize::from(len);
+        if len < 2 || len > rest.len() {
             return None;
         }
 
-        if let Some((usage_page, usage)) = usage_pair {
-            return Some((usage_page, usage));
+        let descriptor = &rest[..len];
+        match kind {
+            INTERFACE if len >= 3 => in_interface = i32::from(descriptor[2]) == interface_number,
+            HID if in_interface && len >= 5 => return Some(descriptor[4]),
+            _ => {}
         }
-    }
-
-    if let (true, Some(usage)) = (initial, usage) {
-        return Some((usage_page, usage));
+        rest = &rest[len..];
     }
 
     None
 }
 
-/// Gets the size of the HID item at the given position
-///
-/// Returns data_len and key_size when successful
-fn hid_item_size(key: u8, cursor: &mut Cursor<&Vec<u8>>) -> Option<(usize, usize)> {
-    // Long Item. Next byte contains the length of the data section.
-    if (key & 0xf0) == 0xf0 {
-        if let Some(Ok(len)) = cursor.bytes().next() {
-            return Some((len.into(), 3));
-        }
-
-        // Malformed report
-        return None;
-    }
-
-    // Short Item. Bottom two bits contains the size code
-    match key & 0x03 {
-        v @ 0..=2 => Some((v.into(), 1)),
-        3 => Some((4, 1)),
-        _ => unreachable!(), // & 0x03 means this can't happen
-    }
+/// The sysfs path of the character device with the given device number
+fn char_device_syspath(devnum: libc::dev_t) -> PathBuf {
+    format!("/sys/dev/char/{}:{}", major(devnum), minor(devnum)).into()
 }
 
-/// Get the bytes from a HID report descriptor
-///
-/// Must only be called with `num_bytes` 0, 1, 2 or 4.
-fn hid_report_bytes(cursor: &mut Cursor<&Vec<u8>>, num_bytes: usize) -> HidResult<u32> {
-    let mut bytes: [u8; 4] = [0; 4];
-    cursor.read_exact(&mut bytes[..num_bytes])?;
+/// Read the report descriptor given the "base" sysfs path of the device
+fn report_descriptor_from_syspath(syspath: &Path) -> HidResult<ReportDescriptor> {
+    let path = syspath.join("device/report_descriptor");
+    let mut f = File::open(path)?;
+    let mut buf = Vec::new();
+    f.read_to_end(&mut buf)?;
 
-    Ok(u32::from_le_bytes(bytes))
+    Ok(ReportDescriptor::from_vec(buf))
 }
 
 /// Get the attribute from the device and convert it into a [`WcharString`].
 fn attribute_as_wchar(dev: &udev::Device, attr: &str) -> WcharString {
     dev.attribute_value(attr)
-        .map(Into::into)
-        .map(osstring_to_string)
+        .map(|v| osstring_to_string(v.to_os_string()))
         .unwrap_or(WcharString::None)
 }
 
@@ -369,8 +293,7 @@ fn attribute_as_wchar(dev: &udev::Device, attr: &str) -> WcharString {
 /// On error or if the attribute is not found, it returns None.
 fn attribute_as_i32(dev: &udev::Device, attr: &str) -> Option<i32> {
     dev.attribute_value(attr)
-        .and_then(OsStr::to_str)
-        .and_then(|v| i32::from_str_radix(v, 16).ok())
+        .and_then(|v| i32::from_str_radix(v.to_str()?, 16).ok())
 }
 
 /// Get the attribute from the device and convert it into a u16
@@ -378,16 +301,70 @@ fn attribute_as_i32(dev: &udev::Device, attr: &str) -> Option<i32> {
 /// On error or if the attribute is not found, it returns None.
 fn attribute_as_u16(dev: &udev::Device, attr: &str) -> Option<u16> {
     dev.attribute_value(attr)
-        .and_then(OsStr::to_str)
-        .and_then(|v| u16::from_str_radix(v, 16).ok())
+        .and_then(|v| u16::from_str_radix(v.to_str()?, 16).ok())
 }
 
 /// Convert a [`OsString`] into a [`WcharString`]
+///
+/// Invalid UTF-8 (e.g. a serial number that is binary garbage) is replaced
+/// with U+FFFD instead of failing the enumeration.
 fn osstring_to_string(s: OsString) -> WcharString {
     match s.into_string() {
         Ok(s) => WcharString::String(s),
-        Err(_) => panic!("udev strings should always be utf8"),
+        Err(s) => WcharString::String(s.to_string_lossy().into_owned()),
+    }
+}
+
+/// Enumerate the hidraw devices, giving up once `deadline` has passed
+fn enumerate(vid: u16, pid: u16, deadline: Option<Instant>) -> HidResult<Vec<DeviceInfo>> {
+    // The C version assumes these can't fail, and they should only fail in case
+    // of memory allocation issues, at which point maybe we should panic
+    let mut enumerator = match udev::Enumerator::new() {
+        Ok(e) => e,
+        Err(_) => return Ok(Vec::new()),
+    };
+    enumerator.match_subsystem("hidraw").unwrap();
+    let scan = match enumerator.scan_devices() {
+        Ok(s) => s,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut devices = Vec::new();
+    for device in scan {
+        check_deadline(deadline)?;
+        devices.extend(
+            device_to_hid_device_info(&device)
+                .into_iter()
+                .flatten()
+                .filter(|device| vid == 0 || device.vendor_id == vid)
+                .filter(|device| pid == 0 || device.product_id == pid),
+        );
+    }
+
+    Ok(devices)
+}
+
+/// Restart a syscall for as long as it is interrupted by a signal
+///
+/// Opening the device needs no loop, the standard library retries `open(2)`
+/// on `EINTR` on its own.
+fn retry_eintr<T>(mut f: impl FnMut() -> nix::Result<T>) -> nix::Result<T> {
+    loop {
+        match f() {
+            Err(Errno::EINTR) => continue,
+            res => return res,
+        }
+    }
+}
+
+/// The part of a poll(2) timeout in milliseconds that is left after `start`
+fn remaining_timeout(timeout: i32, start: Instant) -> i32 {
+    if timeout <= 0 {
+        return timeout;
     }
+
+    let elapsed = start.elapsed().as_millis().min(i32::MAX as u128) as i32;
+    (timeout - elapsed).max(0)
 }
 
 /// Parse a HID_ID string to find the bus type, the vendor and product id
@@ -409,17 +386,25 @@ pub struct HidDevice {
     blocking: Cell<bool>,
     fd: OwnedFd,
     info: RefCell<Option<DeviceInfo>>,
+    /// eventfd used to wake up and cancel blocking reads, created on demand
+    cancel_fd: RefCell<Option<Arc<OwnedFd>>>,
 }
 
 unsafe impl Send for HidDevice {}
 
 // API for the library to call us, or for internal uses
 impl HidDevice {
-    pub(crate) fn open(vid: u16, pid: u16, sn: Option<&str>) -> HidResult<Self> {
-        for device in HidApiBackend::get_hid_device_info_vector(0, 0)?
+    pub(crate) fn open(
+        vid: u16,
+        pid: u16,
+        sn: Option<&str>,
+        deadline: Option<Instant>,
+    ) -> HidResult<Self> {
+        for device in enumerate(vid, pid, deadline)?
             .iter()
             .filter(|device| device.vendor_id == vid && device.product_id == pid)
         {
+            check_deadline(deadline)?;
             match (sn, &device.serial_number) {
                 (None, _) => return Self::open_path(&device.path),
                 (Some(sn), WcharString::String(serial_number)) if sn == serial_number => {
@@ -429,32 +414,55 @@ impl HidDevice {
             };
         }
 
-        Err(HidError::HidApiError {
-            message: "device not found".into(),
-        })
+        Err(HidError::device_not_found(vid, pid, sn))
     }
 
     pub(crate) fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
-        // Paths on Linux can be anything but devnode paths are going to be ASCII
-        let path = device_path.to_str().expect("path must be utf-8");
+        Self::open_path_with_flags(device_path, false)
+    }
+
+    /// Open the hidraw node, with `O_NONBLOCK` unless `blocking` is set
+    pub(crate) fn open_path_with_flags(device_path: &CStr, blocking: bool) -> HidResult<HidDevice> {
+        // Any devnode works, it does not have to come from the enumeration
+        let path = Path::new(OsStr::from_bytes(device_path.to_bytes()));
         let fd: OwnedFd = match OpenOptions::new()
             .read(true)
             .write(true)
-            .custom_flags(libc::O_CLOEXEC | libc::O_NONBLOCK)
+            .custom_flags(if blocking {
+                libc::O_CLOEXEC
+            } else {
+                libc::O_CLOEXEC | libc::O_NONBLOCK
+            })
             .open(path)
         {
             Ok(f) => f.into(),
+            // Both EACCES and EPERM map to this, usually the udev rules are missing
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(HidError::PermissionDenied {
+                    path: path.display().to_string(),
+                });
+            }
             Err(e) => {
                 return Err(HidError::HidApiError {
-                    message: format!("failed to open device with path {path}: {e}"),
+                    message: format!("failed to open device with path {}: {e}", path.display()),
                 });
             }
         };
 
+        match Self::from_fd(fd) {
+            Err(HidError::HidApiError { message }) => Err(HidError::HidApiError {
+                message: format!("{message} for {}", path.display()),
+            }),
+            res => res,
+        }
+    }
+
+    pub(crate) fn from_fd(fd: OwnedFd) -> HidResult<HidDevice> {
         let mut size = 0_i32;
-        if let Err(e) = unsafe { hidraw_ioc_grdescsize(fd.as_raw_fd(), &mut size) } {
+        if let Err(e) = retry_eintr(|| unsafe { hidraw_ioc_grdescsize(fd.as_raw_fd(), &mut size) })
+        {
             return Err(HidError::HidApiError {
-                message: format!("ioctl(GRDESCSIZE) error for {path}, not a HIDRAW device?: {e}"),
+                message: format!("ioctl(GRDESCSIZE) error, not a HIDRAW device?: {e}"),
             });
         }
 
@@ -462,6 +470,7 @@ impl HidDevice {
             blocking: Cell::new(true),
             fd,
             info: RefCell::new(None),
+            cancel_fd: RefCell::new(None),
         })
     }
 
@@ -474,6 +483,138 @@ impl HidDevice {
         let info = self.info.borrow();
         Ok(Ref::map(info, |i: &Option<DeviceInfo>| i.as_ref().unwrap()))
     }
+
+    /// Wait for a report with poll(2) and read it, `None` if the fd turned out
+    /// not to be readable after all
+    fn poll_and_read(&self, buf: &mut [u8], timeout: i32) -> HidResult<Option<usize>> {
+        let cancel_fd = self.cancel_fd.borrow();
+        let mut pollfds = vec![PollFd::new(&self.fd, PollFlags::POLLIN)];
+        if let Some(fd) = cancel_fd.as_deref() {
+            pollfds.push(PollFd::new(fd, PollFlags::POLLIN));
+        }
+        // Interrupted polls are restarted with what is left of the timeout
+        let start = Instant::now();
+        let res = retry_eintr(|| poll(&mut pollfds, remaining_timeout(timeout, start)))?;
+
+        if res == 0 {
+            return Ok(Some(0));
+        }
+
+        if let Some(fd) = cancel_fd.as_deref() {
+            if pollfds[1]
+                .revents()
+                .is_some_and(|e| e.contains(PollFlags::POLLIN))
+            {
+                // Reset the counter, so only this read is cancelled
+                let _ = read(fd.as_raw_fd(), &mut [0u8; 8]);
+                return Err(HidError::Cancelled);
+            }
+        }
+
+        let events = pollfds[0]
+            .revents()
+            .map(|e| e.intersects(PollFlags::POLLERR | PollFlags::POLLHUP | PollFlags::POLLNVAL));
+
+        if events.is_none() || events == Some(true) {
+            return Err(HidError::HidApiError {
+                message: "unexpected poll error (device disconnected)".into(),
+            });
+        }
+
+        match retry_eintr(|| read(self.fd.as_raw_fd(), buf)) {
+            Ok(w) => Ok(Some(w)),
+            Err(Errno::EAGAIN) | Err(Errno::EINPROGRESS) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Read the report descriptor from sysfs, or with an ioctl if sysfs is not
+    /// accessible (e.g. in a sandbox)
+    fn report_descriptor(&self) -> HidResult<ReportDescriptor> {
+        let devnum = fstat(self.fd.as_raw_fd())?.st_rdev;
+        if let Ok(descriptor) = report_descriptor_from_syspath(&char_device_syspath(devnum)) {
+            return Ok(descriptor);
+        }
+
+        let mut size = 0_i32;
+        let mut descriptor = HidrawReportDescriptor {
+            size: 0,
+            value: [0; crate::MAX_REPORT_DESCRIPTOR_SIZE],
+        };
+        let res = retry_eintr(|| unsafe { hidraw_ioc_grdescsize(self.fd.as_raw_fd(), &mut size) })
+            .and_then(|_| {
+                descriptor.size = size as u32;
+                retry_eintr(|| unsafe { hidraw_ioc_grdesc(self.fd.as_raw_fd(), &mut descriptor) })
+            });
+        if let Err(e) = res {
+            return Err(HidError::HidApiError {
+                message: format!("ioctl (GRDESC): {e}"),
+            });
+        }
+
+        let len = (descriptor.size as usize).min(descriptor.value.len());
+        Ok(ReportDescriptor::from_vec(descriptor.value[..len].to_vec()))
+    }
+
+    /// Gather the device info with ioctls, for when sysfs is not accessible
+    ///
+    /// Only the values the kernel reports for hidraw devices are available,
+    /// the USB string descriptors and the interface number are missing.
+    fn device_info_from_ioctls(&self) -> HidResult<DeviceInfo> {
+        let fd = self.fd.as_raw_fd();
+        let mut devinfo = HidrawDevinfo::default();
+        if let Err(e) = retry_eintr(|| unsafe { hidraw_ioc_grawinfo(fd, &mut devinfo) }) {
+            return Err(HidError::HidApiError {
+                message: format!("ioctl (GRAWINFO): {e}"),
+            });
+        }
+
+        let string = |res: nix::Result<i32>, buf: &[u8]| match res {
+            Ok(_) => {
+                let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+                osstring_to_string(OsStr::from_bytes(&buf[..len]).to_os_string())
+            }
+            Err(_) => WcharString::None,
+        };
+        let mut buf = [0u8; 256];
+        let name = string(
+            retry_eintr(|| unsafe { hidraw_ioc_grawname(fd, &mut buf) }),
+            &buf,
+        );
+        let mut buf = [0u8; 256];
+        let serial_number = string(
+            retry_eintr(|| unsafe { hidraw_ioc_grawuniq(fd, &mut buf) }),
+            &buf,
+        );
+
+        // The fd may not have been opened by us, /proc tells where it points to
+        let path = std::fs::read_link(format!("/proc/self/fd/{fd}"))
+            .ok()
+            .and_then(|path| CString::new(path.into_os_string().into_vec()).ok())
+            .unwrap_or_default();
+        let (usage_page, usage) = self
+            .report_descriptor()
+            .ok()
+            .and_then(|descriptor| descriptor.usages().next())
+            .unwrap_or((0, 0));
+
+        let (vendor_id, product_id) = devinfo.vendor_product();
+        Ok(DeviceInfo {
+            path,
+            vendor_id,
+            product_id,
+            serial_number,
+            release_number: 0,
+            manufacturer_string: WcharString::String("".into()),
+            product_string: name.clone(),
+            raw_name: name,
+            usage_page,
+            usage,
+            interface_number: -1,
+            bus_type: BusType::from_linux_bustype(devinfo.bustype),
+            country_code: None,
+        })
+    }
 }
 
 impl AsFd for HidDevice {
@@ -488,7 +629,16 @@ impl HidDeviceBackendBase for HidDevice {
             return Err(HidError::InvalidZeroSizeData);
         }
 
-        Ok(write(self.fd.as_raw_fd(), data)?)
+        // write(2) on hidraw may accept fewer bytes than requested
+        let res = retry_eintr(|| write(self.fd.as_raw_fd(), data))?;
+        if res != data.len() {
+            return Err(HidError::IncompleteSendError {
+                sent: res,
+                all: data.len(),
+            });
+        }
+
+        Ok(res)
     }
 
     fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
@@ -498,28 +648,46 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
-        let pollfd = PollFd::new(&self.fd, PollFlags::POLLIN);
-        let res = poll(&mut [pollfd], timeout)?;
-
-        if res == 0 {
-            return Ok(0);
+        loop {
+            match self.poll_and_read(buf, timeout)? {
+                // The report was taken by someone else between poll() and read(),
+                // a blocking read must not return empty handed
+                None if timeout < 0 => continue,
+                res => return Ok(res.unwrap_or(0)),
+            }
         }
+    }
 
-        let events = pollfd
-            .revents()
-            .map(|e| e.intersects(PollFlags::POLLERR | PollFlags::POLLHUP | PollFlags::POLLNVAL));
+    fn read_canceller(&self) -> HidResult<ReadCanceller> {
+        let mut cancel_fd = self.cancel_fd.borrow_mut();
+        let fd = match cancel_fd.as_ref() {
+            Some(fd) => fd.clone(),
+            None => {
+                let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+                if fd < 0 {
+                    return Err(io::Error::last_os_error().into());
+                }
+                cancel_fd
+                    .insert(Arc::new(unsafe { OwnedFd::from_raw_fd(fd) }))
+                    .clone()
+            }
+        };
 
-        if events.is_none() || events == Some(true) {
-            return Err(HidError::HidApiError {
-                message: "unexpected poll error (device disconnected)".into(),
-            });
-        }
+        Ok(ReadCanceller::new(move || {
+            let _ = write(fd.as_raw_fd(), &1u64.to_ne_bytes());
+        }))
+    }
 
-        match read(self.fd.as_raw_fd(), buf) {
-            Ok(w) => Ok(w),
-            Err(Errno::EAGAIN) | Err(Errno::EINPROGRESS) => Ok(0),
-            Err(e) => Err(e.into()),
-        }
+    fn has_pending_report(&self) -> HidResult<bool> {
+        // Errors and hangups are reported as pending too, so the next read
+        // returns them instead of blocking
+        let pollfd = PollFd::new(&self.fd, PollFlags::POLLIN);
+        Ok(retry_eintr(|| poll(&mut [pollfd], 0))? > 0)
+    }
+
+    fn queued_report_count(&self) -> HidResult<usize> {
+        // The kernel does not tell how many reports are queued
+        Ok(usize::from(self.has_pending_report()?))
     }
 
     fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
@@ -527,11 +695,12 @@ impl HidDeviceBackendBase for HidDevice {
             return Err(HidError::InvalidZeroSizeData);
         }
 
-        let res = match unsafe { hidraw_ioc_set_feature(self.fd.as_raw_fd(), data) } {
+        let res = match retry_eintr(|| unsafe { hidraw_ioc_set_feature(self.fd.as_raw_fd(), data) })
+        {
             Ok(n) => n as usize,
             Err(e) => {
                 return Err(HidError::HidApiError {
-                    message: format!("ioctl (GFEATURE): {e}"),
+                    message: format!("ioctl (SFEATURE): {e}"),
                 })
             }
         };
@@ -547,7 +716,8 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
-        let res = match unsafe { hidraw_ioc_get_feature(self.fd.as_raw_fd(), buf) } {
+        let res = match retry_eintr(|| unsafe { hidraw_ioc_get_feature(self.fd.as_raw_fd(), buf) })
+        {
             Ok(n) => n as usize,
             Err(e) => {
                 return Err(HidError::HidApiError {
@@ -560,7 +730,11 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn send_output_report(&self, buf: &[u8]) -> HidResult<()> {
-        let res = match unsafe { hidraw_ioc_set_output(self.fd.as_raw_fd(), buf) } {
+        if buf.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+
+        let res = match retry_eintr(|| unsafe { hidraw_ioc_set_output(self.fd.as_raw_fd(), buf) }) {
             Ok(n) => n,
             Err(e) => {
                 return Err(HidError::HidApiError {
@@ -580,7 +754,7 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize> {
-        match unsafe { hidraw_ioc_get_input(self.fd.as_raw_fd(), data) } {
+        match retry_eintr(|| unsafe { hidraw_ioc_get_input(self.fd.as_raw_fd(), data) }) {
             Ok(n) => Ok(n as usize),
             Err(e) => Err(HidError::HidApiError {
                 message: format!("ioctl (GINPUT): {e}"),
@@ -588,11 +762,48 @@ impl HidDeviceBackendBase for HidDevice {
         }
     }
 
+    fn set_input_report(&self, data: &[u8]) -> HidResult<()> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+
+        match retry_eintr(|| unsafe { hidraw_ioc_set_input(self.fd.as_raw_fd(), data) }) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(HidError::HidApiError {
+                message: format!("ioctl (SINPUT): {e}"),
+            }),
+        }
+    }
+
+    fn into_fd(self: Box<Self>) -> HidResult<OwnedFd> {
+        Ok(self.fd)
+    }
+
+    #[cfg(feature = "linux-raw-ioctl")]
+    unsafe fn ioctl_raw(&self, request: u32, buf: &mut [u8]) -> HidResult<i32> {
+        match retry_eintr(|| {
+            Errno::result(libc::ioctl(
+                self.fd.as_raw_fd(),
+                request as _,
+                buf.as_mut_ptr(),
+            ))
+        }) {
+            Ok(n) => Ok(n),
+            Err(e) => Err(HidError::HidApiError {
+                message: format!("ioctl ({request:#x}): {e}"),
+            }),
+        }
+    }
+
     fn set_blocking_mode(&self, blocking: bool) -> HidResult<()> {
         self.blocking.set(blocking);
         Ok(())
     }
 
+    fn is_blocking(&self) -> bool {
+        self.blocking.get()
+    }
+
     fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
         let info = self.info()?;
         Ok(info.manufacturer_string().map(str::to_string))
@@ -612,11 +823,14 @@ impl HidDeviceBackendBase for HidDevice {
         // What we have is a descriptor to a file in /dev but we need a syspath
         // so we get the major/minor from there and generate our syspath
         let devnum = fstat(self.fd.as_raw_fd())?.st_rdev;
-        let syspath: PathBuf = format!("/sys/dev/char/{}:{}", major(devnum), minor(devnum)).into();
+        let syspath = char_device_syspath(devnum);
 
         // The clone is a bit silly but we can't implement Copy. Maybe it's not
         // much worse than doing the conversion to Rust from interacting with C.
-        let device = udev::Device::from_syspath(&syspath)?;
+        let device = match udev::Device::from_syspath(&syspath) {
+            Ok(device) => device,
+            Err(_) => return self.device_info_from_ioctls(),
+        };
         match device_to_hid_device_info(&device) {
             Some(info) => Ok(info[0].clone()),
             None => Err(HidError::HidApiError {
@@ -626,12 +840,10 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
-        let devnum = fstat(self.fd.as_raw_fd())?.st_rdev;
-        let syspath: PathBuf = format!("/sys/dev/char/{}:{}", major(devnum), minor(devnum)).into();
-
-        let descriptor = HidrawReportDescriptor::from_syspath(&syspath)?;
-        let min_size = buf.len().min(descriptor.0.len());
-        buf[..min_size].copy_from_slice(&descriptor.0[..min_size]);
+        let descriptor = self.report_descriptor()?;
+        let descriptor = descriptor.as_ref();
+        let min_size = buf.len().min(descriptor.len());
+        buf[..min_size].copy_from_slice(&descriptor[..min_size]);
         Ok(min_size)
     }
 }
@@ -639,30 +851,215 @@ impl HidDeviceBackendBase for HidDevice {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::test::test_info;
 
     #[test]
     fn test_parse_hid_vid_pid() {
         assert_eq!(None, parse_hid_vid_pid("Hello World"));
         assert_eq!(Some((1, 1, 1)), parse_hid_vid_pid("1:1:1"));
         assert_eq!(Some((0x11, 0x17, 0x18)), parse_hid_vid_pid("11:0017:00018"));
+        assert_eq!(
+            Some((0x03, 0xffff, 0x8001)),
+            parse_hid_vid_pid("0003:0000FFFF:00008001")
+        );
+    }
+
+    #[test]
+    fn test_devinfo_vendor_product() {
+        let devinfo = HidrawDevinfo {
+            vendor: -1,
+            product: i16::MIN,
+            ..Default::default()
+        };
+        assert_eq!((0xffff, 0x8000), devinfo.vendor_product());
+    }
+
+    #[test]
+    fn test_osstring_to_string_lossy() {
+        let s = OsString::from_vec(vec![b'A', 0xff, b'B']);
+        assert!(osstring_to_string(s) == WcharString::String("A\u{FFFD}B".into()));
+    }
+
+    #[test]
+    fn test_retry_eintr() {
+        let mut calls = 0;
+        let res = retry_eintr(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(Errno::EINTR)
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(Ok(3), res);
+
+        assert_eq!(Err::<(), _>(Errno::EIO), retry_eintr(|| Err(Errno::EIO)));
     }
 
     #[test]
-    fn test_hidraw_report_descriptor_1() {
-        let data = include_bytes!("../tests/assets/mouse1.data");
-        let desc = HidrawReportDescriptor::from_slice(&data[..]).expect("descriptor");
-        let values = desc.usages().collect::<Vec<_>>();
+    fn test_remaining_timeout() {
+        let start = Instant::now();
+        assert_eq!(-1, remaining_timeout(-1, start));
+        assert_eq!(0, remaining_timeout(0, start));
+        assert!(remaining_timeout(10_000, start) > 9_000);
+
+        let start = start - std::time::Duration::from_secs(1);
+        assert_eq!(0, remaining_timeout(100, start));
+    }
 
-        assert_eq!(vec![(65468, 136)], values);
+    /// A device which is not backed by a hidraw node, for the checks which
+    /// happen before talking to the kernel
+    fn test_device() -> HidDevice {
+        test_device_from_fd(File::open("/dev/null").unwrap().into())
+    }
+
+    fn test_device_from_fd(fd: OwnedFd) -> HidDevice {
+        HidDevice {
+            blocking: Cell::new(true),
+            fd,
+            info: RefCell::new(None),
+            cancel_fd: RefCell::new(None),
+        }
     }
 
     #[test]
-    fn test_hidraw_report_descriptor_2() {
-        let data = include_bytes!("../tests/assets/mouse2.data");
-        let desc = HidrawReportDescriptor::from_slice(&data[..]).expect("descriptor");
-        let values = desc.usages().collect::<Vec<_>>();
+    fn test_hid_country_code() {
+        #[rustfmt::skip]
+        let descriptors = [
+            // Device descriptor (shortened) and configuration descriptor
+            0x04, 0x01, 0x00, 0x02,
+            0x09, 0x02, 0x3b, 0x00, 0x02, 0x01, 0x00, 0xa0, 0x32,
+            // Interface 0 with HID descriptor, country code 0
+            0x09, 0x04, 0x00, 0x00, 0x01, 0x03, 0x01, 0x01, 0x00,
+            0x09, 0x21, 0x11, 0x01, 0x00, 0x01, 0x22, 0x41, 0x00,
+            // Interface 1 with HID descriptor, country code 9 (German)
+            0x09, 0x04, 0x01, 0x00, 0x01, 0x03, 0x00, 0x00, 0x00,
+            0x09, 0x21, 0x11, 0x01, 0x09, 0x01, 0x22, 0x36, 0x00,
+        ];
+
+        assert_eq!(Some(0), hid_country_code(&descriptors, 0));
+        assert_eq!(Some(9), hid_country_code(&descriptors, 1));
+        assert_eq!(None, hid_country_code(&descriptors, 2));
+        assert_eq!(None, hid_country_code(&descriptors[..30], 1));
+        assert_eq!(None, hid_country_code(&[0x00, 0x04], 0));
+    }
+
+    #[test]
+    fn test_open_fd_errors() {
+        // Not a hidraw device, the fd is still closed again
+        let (stream, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+        assert!(HidApiBackend::open_fd(stream.into()).is_err());
+    }
+
+    #[test]
+    fn test_empty_buffers() {
+        let device = crate::HidDevice::from_backend(Box::new(test_device()));
+        let is_zero_size = |res| matches!(res, Err(HidError::InvalidZeroSizeData));
+
+        assert!(is_zero_size(device.read(&mut [])));
+        assert!(is_zero_size(device.read_timeout(&mut [], 0)));
+        assert!(is_zero_size(device.get_feature_report(&mut [])));
+        assert!(is_zero_size(device.get_input_report(&mut [])));
+        assert_eq!(0, device.stats().read_errors);
+    }
+
+    #[test]
+    fn test_into_fd() {
+        let (stream, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+        let raw_fd = stream.as_raw_fd();
+        let device = Box::new(test_device_from_fd(stream.into()));
+
+        // Still open, and not closed a second time when dropped
+        let fd = device.into_fd().unwrap();
+        assert_eq!(raw_fd, fd.as_raw_fd());
+        assert!(fstat(raw_fd).is_ok());
+    }
+
+    #[test]
+    fn test_open_path_errors() {
+        // Not valid UTF-8, which must not be a problem for a path
+        let path = CString::new(b"/nonexistent/hidraw\xff".to_vec()).unwrap();
+        assert!(HidDevice::open_path(&path).is_err());
+
+        let path = CString::new("/dev/null").unwrap();
+        assert!(HidDevice::open_path(&path).is_err());
+        assert!(HidDevice::open_path_with_flags(&path, true).is_err());
+    }
+
+    #[test]
+    fn test_zero_size_writes() {
+        let device = test_device();
+
+        assert!(matches!(
+            device.write(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+        assert!(matches!(
+            device.send_feature_report(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+        assert!(matches!(
+            device.send_output_report(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+    }
+
+    #[test]
+    fn test_cancel_reads() {
+        // Never becomes readable while the other end is kept open
+        let (stream, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+        let device = test_device_from_fd(stream.into());
+        let canceller = device.read_canceller().unwrap();
+
+        let reader = std::thread::spawn(move || {
+            let mut buf = [0u8; 8];
+            let res = device.read(&mut buf);
+            (device, res)
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        canceller.cancel();
+
+        let (device, res) = reader.join().unwrap();
+        assert!(matches!(res, Err(HidError::Cancelled)));
+        // Only the read in progress is cancelled
+        assert_eq!(0, device.read_timeout(&mut [0u8; 8], 0).unwrap());
+    }
+
+    #[cfg(feature = "linux-raw-ioctl")]
+    #[test]
+    fn test_ioctl_raw() {
+        // HIDIOCGFEATURE(64) and HIDIOCGRDESCSIZE from linux/hidraw.h
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        {
+            assert_eq!(0xc0404807, hidraw_request_code(true, true, 0x07, 64));
+            assert_eq!(0x80044801, hidraw_request_code(true, false, 0x01, 4));
+        }
+
+        // Not a hidraw device, so the kernel rejects the request
+        let (stream, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+        let device = test_device_from_fd(stream.into());
+        let mut buf = [0u8; 4];
+        let request = hidraw_request_code(true, false, 0x01, buf.len());
+        assert!(unsafe { device.ioctl_raw(request, &mut buf) }.is_err());
+    }
+
+    #[test]
+    fn test_expand_usages_without_usages() {
+        let infos = expand_usages(test_info(), Vec::new());
+
+        assert_eq!(1, infos.len());
+        assert_eq!((0, 0), (infos[0].usage_page, infos[0].usage));
+    }
+
+    #[test]
+    fn test_expand_usages() {
+        let infos = expand_usages(test_info(), vec![(1, 2), (12, 1)]);
+        let values = infos
+            .iter()
+            .map(|info| (info.usage_page, info.usage))
+            .collect::<Vec<_>>();
 
-        let expected = vec![(1, 2), (1, 1), (1, 128), (12, 1), (65280, 14)];
-        assert_eq!(expected, values);
+        assert_eq!(vec![(1, 2), (12, 1)], values);
+        assert!(infos.iter().all(|info| info.path == test_info().path));
     }
 }
@@ -5,17 +5,73 @@ use nix::{ioctl_read, ioctl_read_buf, ioctl_write_buf};
 // From linux/hidraw.h
 const HIDRAW_IOC_MAGIC: u8 = b'H';
 const HIDRAW_IOC_GRDESCSIZE: u8 = 0x01;
+const HIDRAW_IOC_GRDESC: u8 = 0x02;
+const HIDRAW_IOC_GRAWINFO: u8 = 0x03;
+const HIDRAW_IOC_GRAWNAME: u8 = 0x04;
+const HIDRAW_IOC_GRAWUNIQ: u8 = 0x08;
 const HIDRAW_SET_FEATURE: u8 = 0x06;
 const HIDRAW_GET_FEATURE: u8 = 0x07;
 const HIDRAW_SET_OUTPUT: u8 = 0x0b;
+const HIDRAW_SET_INPUT: u8 = 0x09;
 const HIDRAW_GET_INPUT: u8 = 0x0a;
 
+/// `struct hidraw_report_descriptor`
+#[repr(C)]
+pub struct HidrawReportDescriptor {
+    pub size: u32,
+    pub value: [u8; crate::MAX_REPORT_DESCRIPTOR_SIZE],
+}
+
+/// `struct hidraw_devinfo`
+///
+/// The kernel declares vendor and product as `__s16`, so the fields keep that
+/// type for the ioctl. Use [`HidrawDevinfo::vendor_product`] to get the ids.
+#[repr(C)]
+#[derive(Default)]
+pub struct HidrawDevinfo {
+    pub bustype: u32,
+    pub vendor: i16,
+    pub product: i16,
+}
+
+impl HidrawDevinfo {
+    /// The vendor and product id, reinterpreting the signed kernel values, so
+    /// e.g. a vendor of -1 is 0xffff
+    pub fn vendor_product(&self) -> (u16, u16) {
+        (self.vendor as u16, self.product as u16)
+    }
+}
+
 ioctl_read!(
     hidraw_ioc_grdescsize,
     HIDRAW_IOC_MAGIC,
     HIDRAW_IOC_GRDESCSIZE,
     libc::c_int
 );
+ioctl_read!(
+    hidraw_ioc_grdesc,
+    HIDRAW_IOC_MAGIC,
+    HIDRAW_IOC_GRDESC,
+    HidrawReportDescriptor
+);
+ioctl_read!(
+    hidraw_ioc_grawinfo,
+    HIDRAW_IOC_MAGIC,
+    HIDRAW_IOC_GRAWINFO,
+    HidrawDevinfo
+);
+ioctl_read_buf!(
+    hidraw_ioc_grawname,
+    HIDRAW_IOC_MAGIC,
+    HIDRAW_IOC_GRAWNAME,
+    u8
+);
+ioctl_read_buf!(
+    hidraw_ioc_grawuniq,
+    HIDRAW_IOC_MAGIC,
+    HIDRAW_IOC_GRAWUNIQ,
+    u8
+);
 
 ioctl_write_buf!(
     hidraw_ioc_set_feature,
@@ -35,4 +91,23 @@ ioctl_write_buf!(
     HIDRAW_SET_OUTPUT,
     u8
 );
+ioctl_write_buf!(hidraw_ioc_set_input, HIDRAW_IOC_MAGIC, HIDRAW_SET_INPUT, u8);
 ioctl_read_buf!(hidraw_ioc_get_input, HIDRAW_IOC_MAGIC, HIDRAW_GET_INPUT, u8);
+
+/// Build the request number of a hidraw ioctl, like the kernel's `_IOC('H', nr, len)`
+///
+/// `read` and `write` give the direction of the data transfer as seen from
+/// userspace, `len` is the size of the argument in bytes. The encoding depends on
+/// the architecture, so this should be used instead of hardcoded numbers.
+#[cfg(feature = "linux-raw-ioctl")]
+pub fn hidraw_request_code(read: bool, write: bool, nr: u8, len: usize) -> u32 {
+    use nix::{request_code_none, request_code_read, request_code_readwrite, request_code_write};
+
+    let code = match (read, write) {
+        (false, false) => request_code_none!(HIDRAW_IOC_MAGIC, nr),
+        (true, false) => request_code_read!(HIDRAW_IOC_MAGIC, nr, len),
+        (false, true) => request_code_write!(HIDRAW_IOC_MAGIC, nr, len),
+        (true, true) => request_code_readwrite!(HIDRAW_IOC_MAGIC, nr, len),
+    };
+    code as u32
+}
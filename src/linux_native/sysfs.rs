@@ -0,0 +1,163 @@
+//! A minimal replacement for the parts of the `udev` crate used by the native
+//! linux backend, which reads sysfs directly so no libudev is required
+
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fs, io,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+/// Lists the devices of a subsystem from `/sys/class`
+pub struct Enumerator {
+    subsystem: Option<String>,
+}
+
+impl Enumerator {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self { subsystem: None })
+    }
+
+    pub fn match_subsystem(&mut self, subsystem: &str) -> io::Result<()> {
+        self.subsystem = Some(subsystem.to_string());
+        Ok(())
+    }
+
+    pub fn scan_devices(&mut self) -> io::Result<impl Iterator<Item = Device>> {
+        let subsystem = self
+            .subsystem
+            .as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no subsystem to scan"))?;
+        let entries = fs::read_dir(Path::new("/sys/class").join(subsystem))?;
+
+        Ok(entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| Device::from_syspath(&entry.path()).ok()))
+    }
+}
+
+/// A device in the sysfs tree
+pub struct Device {
+    syspath: PathBuf,
+    subsystem: Option<OsString>,
+    devnode: Option<PathBuf>,
+    properties: HashMap<OsString, OsString>,
+}
+
+impl Device {
+    pub fn from_syspath(syspath: &Path) -> io::Result<Self> {
+        // Entries in /sys/class and /sys/dev are symlinks into /sys/devices, the
+        // parents can only be found from the resolved path
+        let syspath = syspath.canonicalize()?;
+        let properties = parse_uevent(&fs::read(syspath.join("uevent"))?);
+        let subsystem = fs::read_link(syspath.join("subsystem"))
+            .ok()
+            .and_then(|link| link.file_name().map(OsStr::to_os_string));
+        let devnode = properties
+            .get(OsStr::new("DEVNAME"))
+            .map(|name| Path::new("/dev").join(name));
+
+        Ok(Self {
+            syspath,
+            subsystem,
+            devnode,
+            properties,
+        })
+    }
+
+    pub fn syspath(&self) -> &Path {
+        &self.syspath
+    }
+
+    pub fn devnode(&self) -> Option<&Path> {
+        self.devnode.as_deref()
+    }
+
+    pub fn property_value<T: AsRef<OsStr>>(&self, property: T) -> Option<&OsStr> {
+        self.properties
+            .get(property.as_ref())
+            .map(OsString::as_os_str)
+    }
+
+    /// Read a sysfs attribute of the device
+    ///
+    /// Unlike with libudev the attributes are not cached, so this returns an
+    /// owned value.
+    pub fn attribute_value<T: AsRef<OsStr>>(&self, attribute: T) -> Option<OsString> {
+        let mut value = fs::read(self.syspath.join(attribute.as_ref())).ok()?;
+        while value.last() == Some(&b'\n') {
+            value.pop();
+        }
+
+        Some(OsString::from_vec(value))
+    }
+
+    pub fn parent_with_subsystem<T: AsRef<OsStr>>(&self, subsystem: T) -> io::Result<Option<Self>> {
+        self.find_parent(subsystem.as_ref(), None)
+    }
+
+    pub fn parent_with_subsystem_devtype<T: AsRef<OsStr>, U: AsRef<OsStr>>(
+        &self,
+        subsystem: T,
+        devtype: U,
+    ) -> io::Result<Option<Self>> {
+        self.find_parent(subsystem.as_ref(), Some(devtype.as_ref()))
+    }
+
+    fn find_parent(&self, subsystem: &OsStr, devtype: Option<&OsStr>) -> io::Result<Option<Self>> {
+        for path in self.syspath.ancestors().skip(1) {
+            if !path.starts_with("/sys/devices") {
+                break;
+            }
+
+            // Not every directory on the way up is a device
+            if !path.join("uevent").exists() {
+                continue;
+            }
+
+            let parent = Self::from_syspath(path)?;
+            if parent.subsystem.as_deref() == Some(subsystem)
+                && (devtype.is_none() || parent.property_value("DEVTYPE") == devtype)
+            {
+                return Ok(Some(parent));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Parse the `KEY=value` lines of a sysfs uevent file
+fn parse_uevent(data: &[u8]) -> HashMap<OsString, OsString> {
+    data.split(|c| *c == b'\n')
+        .filter_map(|line| {
+            let split = line.iter().position(|c| *c == b'=')?;
+            Some((
+                OsStr::from_bytes(&line[..split]).to_os_string(),
+                OsStr::from_bytes(&line[split + 1..]).to_os_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_uevent() {
+        let data = b"DRIVER=hid-generic\nHID_ID=0003:0000046D:0000C52B\nHID_UNIQ=\n";
+        let properties = parse_uevent(data);
+
+        assert_eq!(3, properties.len());
+        assert_eq!(
+            Some(&OsString::from("0003:0000046D:0000C52B")),
+            properties.get(OsStr::new("HID_ID"))
+        );
+        assert_eq!(
+            Some(&OsString::new()),
+            properties.get(OsStr::new("HID_UNIQ"))
+        );
+    }
+}
@@ -1,7 +1,23 @@
-use libc::c_int;
+use std::ffi::CStr;
+
+use libc::{c_int, c_void};
 
 use crate::ffi;
-use crate::{HidApi, HidDevice, HidResult};
+use crate::{AccessStatus, HidApi, HidDevice, HidError, HidResult};
+
+// From IOKit/IOReturn.h
+const IO_RETURN_SUCCESS: u32 = 0;
+const IO_RETURN_NOT_PRIVILEGED: u32 = 0xe00002c1;
+const IO_RETURN_EXCLUSIVE_ACCESS: u32 = 0xe00002c5;
+const IO_RETURN_NOT_PERMITTED: u32 = 0xe00002e2;
+
+// From IOKit/hid/IOHIDKeys.h
+const IOHID_OPTIONS_TYPE_NONE: u32 = 0;
+
+// From IOKit/hid/IOHIDLib.h
+const IOHID_REQUEST_TYPE_LISTEN_EVENT: u32 = 1;
+const IOHID_ACCESS_TYPE_GRANTED: u32 = 0;
+const IOHID_ACCESS_TYPE_DENIED: u32 = 1;
 
 impl HidApi {
     /// Changes the behavior of all further calls that open a new [`HidDevice`]
@@ -19,6 +35,36 @@ impl HidApi {
     pub fn get_open_exclusive(&self) -> bool {
         unsafe { ffi::macos::hid_darwin_get_open_exclusive() != 0 }
     }
+
+    /// Check if the device with the given vid and pid can be opened, or if another
+    /// program has seized it.
+    ///
+    /// The first matching device is opened non-exclusively with IOKit and closed
+    /// again right away, so this neither disturbs programs which use the device
+    /// nor depends on the mode set with [`HidApi::set_open_exclusive`]. A device
+    /// which another program opened non-exclusively is therefore available.
+    ///
+    /// Returns `Ok(false)` if opening failed with `kIOReturnExclusiveAccess` or
+    /// `kIOReturnNotPermitted`, and [`HidError::PermissionDenied`] for
+    /// `kIOReturnNotPrivileged`. All other errors are passed on.
+    pub fn is_device_available(&self, vid: u16, pid: u16) -> HidResult<bool> {
+        let info = self
+            .enumerate(vid, pid)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| HidError::device_not_found(vid, pid, None))?;
+
+        match probe_open(info.path())? {
+            IO_RETURN_SUCCESS => Ok(true),
+            IO_RETURN_EXCLUSIVE_ACCESS | IO_RETURN_NOT_PERMITTED => Ok(false),
+            IO_RETURN_NOT_PRIVILEGED => Err(HidError::PermissionDenied {
+                path: info.path().to_string_lossy().into_owned(),
+            }),
+            code => Err(HidError::HidApiError {
+                message: format!("failed to open IOHIDDevice: (0x{code:08X})"),
+            }),
+        }
+    }
 }
 
 impl HidDevice {
@@ -31,4 +77,244 @@ impl HidDevice {
     pub fn is_open_exclusive(&self) -> HidResult<bool> {
         self.inner.is_open_exclusive()
     }
+
+    /// Read a string property of the device from the IORegistry, e.g. one a vendor
+    /// stores calibration or firmware data in.
+    ///
+    /// Returns `Ok(None)` if the device has no property `key` or it is not a string.
+    pub fn get_custom_property(&self, key: &str) -> HidResult<Option<String>> {
+        self.inner.get_custom_property(key)
+    }
+
+    /// Like [`HidDevice::get_custom_property`], for numeric properties which fit
+    /// into an `i32`.
+    pub fn get_custom_property_i32(&self, key: &str) -> HidResult<Option<i32>> {
+        self.inner.get_custom_property_i32(key)
+    }
+}
+
+/// Read the property `key` of the IORegistry entry of the device at `path`, and
+/// convert it with `convert` if it exists
+pub(crate) fn registry_property<T>(
+    path: &CStr,
+    key: &str,
+    convert: impl FnOnce(ffi::macos::CFTypeRef) -> Option<T>,
+) -> HidResult<Option<T>> {
+    use ffi::macos::*;
+
+    let service = registry_service(path)?;
+    unsafe {
+        let key = CFStringCreateWithBytes(
+            std::ptr::null(),
+            key.as_ptr(),
+            key.len() as CFIndex,
+            K_CF_STRING_ENCODING_UTF8,
+            0,
+        );
+        let property = if key.is_null() {
+            std::ptr::null()
+        } else {
+            let property = IORegistryEntryCreateCFProperty(service, key, std::ptr::null(), 0);
+            CFRelease(key);
+            property
+        };
+        IOObjectRelease(service);
+
+        if property.is_null() {
+            return Ok(None);
+        }
+        let value = convert(property);
+        CFRelease(property);
+        Ok(value)
+    }
+}
+
+/// Look up the IORegistry entry of the device at `path`, which the caller has to
+/// release with `IOObjectRelease`
+///
+/// The C library names devices `DevSrvsID:<registry entry id>`.
+fn registry_service(path: &CStr) -> HidResult<u32> {
+    use ffi::macos::*;
+
+    let entry_id = path
+        .to_str()
+        .ok()
+        .and_then(|path| path.strip_prefix("DevSrvsID:"))
+        .and_then(|id| id.parse::<u64>().ok())
+        .ok_or_else(|| HidError::HidApiError {
+            message: format!("no IORegistry entry id in device path {path:?}"),
+        })?;
+
+    // IOServiceGetMatchingService consumes the matching dictionary
+    let service = unsafe { IOServiceGetMatchingService(0, IORegistryEntryIDMatching(entry_id)) };
+    if service == 0 {
+        return Err(HidError::HidApiError {
+            message: format!("IORegistry entry {entry_id} not found"),
+        });
+    }
+    Ok(service)
+}
+
+/// Open the device at `path` non-exclusively and close it again, returning the
+/// `IOReturn` code of the open
+fn probe_open(path: &CStr) -> HidResult<u32> {
+    use ffi::macos::*;
+
+    let service = registry_service(path)?;
+    unsafe {
+        let device = IOHIDDeviceCreate(std::ptr::null(), service);
+        IOObjectRelease(service);
+        if device.is_null() {
+            return Err(HidError::HidApiError {
+                message: format!("failed to create IOHIDDevice for {path:?}"),
+            });
+        }
+
+        let code = IOHIDDeviceOpen(device, IOHID_OPTIONS_TYPE_NONE) as u32;
+        if code == IO_RETURN_SUCCESS {
+            IOHIDDeviceClose(device, IOHID_OPTIONS_TYPE_NONE);
+        }
+        CFRelease(device);
+        Ok(code)
+    }
+}
+
+/// Convert a CoreFoundation string, `None` if `cf` is something else
+pub(crate) fn cf_string(cf: ffi::macos::CFTypeRef) -> Option<String> {
+    use ffi::macos::*;
+
+    unsafe {
+        if CFGetTypeID(cf) != CFStringGetTypeID() {
+            return None;
+        }
+
+        let len =
+            CFStringGetMaximumSizeForEncoding(CFStringGetLength(cf), K_CF_STRING_ENCODING_UTF8);
+        let mut buf = vec![0u8; len as usize + 1];
+        if CFStringGetCString(
+            cf,
+            buf.as_mut_ptr().cast(),
+            buf.len() as CFIndex,
+            K_CF_STRING_ENCODING_UTF8,
+        ) == 0
+        {
+            return None;
+        }
+
+        CStr::from_bytes_until_nul(&buf)
+            .ok()
+            .map(|s| s.to_string_lossy().into_owned())
+    }
+}
+
+/// Convert a CoreFoundation number, `None` if `cf` is something else or the
+/// number does not fit into an `i32`
+pub(crate) fn cf_i32(cf: ffi::macos::CFTypeRef) -> Option<i32> {
+    use ffi::macos::*;
+
+    unsafe {
+        if CFGetTypeID(cf) != CFNumberGetTypeID() {
+            return None;
+        }
+
+        let mut value = 0i32;
+        let exact = CFNumberGetValue(cf, K_CF_NUMBER_SINT32_TYPE, (&mut value as *mut i32).cast());
+        (exact != 0).then_some(value)
+    }
+}
+
+/// Extract the `IOReturn` code from an error message of the form
+/// `hid_open_path: failed to open IOHIDDevice from mach entry: (0xE00002C5) ...`.
+///
+/// This depends on the wording of the C library's message, `None` if it changes.
+fn io_return_code(message: &str) -> Option<u32> {
+    let start = message.find("(0x")? + 3;
+    let len = message[start..].find(')')?;
+    u32::from_str_radix(&message[start..start + len], 16).ok()
+}
+
+/// Turn a failure to open the device at `path` with `kIOReturnNotPrivileged`
+/// into [`HidError::PermissionDenied`], and pass other errors on
+pub(crate) fn open_path_error(error: HidError, path: &CStr) -> HidError {
+    match &error {
+        HidError::HidApiError { message }
+            if io_return_code(message) == Some(IO_RETURN_NOT_PRIVILEGED) =>
+        {
+            HidError::PermissionDenied {
+                path: path.to_string_lossy().into_owned(),
+            }
+        }
+        _ => error,
+    }
+}
+
+/// Look up a function of IOKit, which may not exist on older macOS versions
+fn iokit_function(name: &[u8]) -> Option<*mut c_void> {
+    debug_assert_eq!(Some(&0), name.last());
+    let function = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr().cast()) };
+    (!function.is_null()).then_some(function)
+}
+
+pub(crate) fn check_access() -> AccessStatus {
+    let Some(function) = iokit_function(b"IOHIDCheckAccess\0") else {
+        return AccessStatus::Unknown;
+    };
+    let check_access: unsafe extern "C" fn(u32) -> u32 = unsafe { std::mem::transmute(function) };
+
+    match unsafe { check_access(IOHID_REQUEST_TYPE_LISTEN_EVENT) } {
+        IOHID_ACCESS_TYPE_GRANTED => AccessStatus::Granted,
+        IOHID_ACCESS_TYPE_DENIED => AccessStatus::Denied,
+        _ => AccessStatus::Unknown,
+    }
+}
+
+pub(crate) fn request_access() -> HidResult<bool> {
+    let Some(function) = iokit_function(b"IOHIDRequestAccess\0") else {
+        return Err(HidError::HidApiError {
+            message: "request_access: not supported".to_string(),
+        });
+    };
+    let request_access: unsafe extern "C" fn(u32) -> bool =
+        unsafe { std::mem::transmute(function) };
+
+    Ok(unsafe { request_access(IOHID_REQUEST_TYPE_LISTEN_EVENT) })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_io_return_code() {
+        assert_eq!(
+            Some(IO_RETURN_EXCLUSIVE_ACCESS),
+            io_return_code(
+                "hid_open_path: failed to open IOHIDDevice from mach entry: (0xE00002C5) (iokit/common) exclusive access and device already open"
+            )
+        );
+        assert_eq!(None, io_return_code("hid_open_path: (0xE00002C5"));
+        assert_eq!(None, io_return_code("hid_open_path: (0xfoo)"));
+        assert_eq!(None, io_return_code("hid_open: device not found"));
+    }
+
+    #[test]
+    fn test_open_path_error() {
+        let path = c"DevSrvsID:4294968542";
+        let not_privileged = HidError::HidApiError {
+            message: "hid_open_path: failed to open IOHIDDevice from mach entry: (0xE00002C1) (iokit/common) privilege violation".into(),
+        };
+        assert!(matches!(
+            open_path_error(not_privileged, path),
+            HidError::PermissionDenied { path } if path == "DevSrvsID:4294968542"
+        ));
+
+        let other = HidError::HidApiError {
+            message: "hid_open_path: failed to open IOHIDDevice from mach entry: (0xE00002C5)"
+                .into(),
+        };
+        assert!(matches!(
+            open_path_error(other, path),
+            HidError::HidApiError { .. }
+        ));
+    }
 }
@@ -0,0 +1,399 @@
+//! A virtual backend, which allows testing code using this crate without hardware
+//!
+//! ```
+//! use hidapi::{mock::MockDevice, HidApi};
+//!
+//! let device = MockDevice::new(0x046d, 0xc52b).product_string("Receiver");
+//! let api = HidApi::new_mocked(vec![device.clone()]);
+//!
+//! let handle = api.open(0x046d, 0xc52b).unwrap();
+//! device.queue_input_report(&[0x01, 0x02]);
+//!
+//! let mut buf = [0u8; 8];
+//! assert_eq!(2, handle.read(&mut buf).unwrap());
+//! ```
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    ffi::{CStr, CString},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::{Capabilities, DeviceInfo, HidDevice, HidDeviceBackendBase, HidError, HidResult};
+use crate::{WcharString, MAX_REPORT_DESCRIPTOR_SIZE};
+
+/// A fake device, to be passed to [`HidApi::new_mocked`](crate::HidApi::new_mocked)
+///
+/// Clones share the reports of the device, so a clone can be kept to queue Input
+/// reports and inspect what was sent to the device after creating the `HidApi`.
+#[derive(Clone)]
+pub struct MockDevice {
+    pub(crate) info: DeviceInfo,
+    report_descriptor: Vec<u8>,
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    input_available: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    input_reports: VecDeque<Vec<u8>>,
+    written_reports: Vec<Vec<u8>>,
+    feature_reports: HashMap<u8, Vec<u8>>,
+}
+
+impl MockDevice {
+    pub fn new(vendor_id: u16, product_id: u16) -> Self {
+        Self {
+            info: DeviceInfo::new(CString::default(), vendor_id, product_id),
+            report_descriptor: Vec::new(),
+            shared: Arc::default(),
+        }
+    }
+
+    pub fn serial_number(mut self, serial_number: &str) -> Self {
+        self.info.serial_number = WcharString::String(serial_number.into());
+        self
+    }
+
+    pub fn manufacturer_string(mut self, manufacturer_string: &str) -> Self {
+        self.info.manufacturer_string = WcharString::String(manufacturer_string.into());
+        self
+    }
+
+    pub fn product_string(mut self, product_string: &str) -> Self {
+        self.info.product_string = WcharString::String(product_string.into());
+        self
+    }
+
+    pub fn usage(mut self, usage_page: u16, usage: u16) -> Self {
+        self.info.usage_page = usage_page;
+        self.info.usage = usage;
+        self
+    }
+
+    pub fn interface_number(mut self, interface_number: i32) -> Self {
+        self.info.interface_number = interface_number;
+        self
+    }
+
+    pub fn report_descriptor(mut self, report_descriptor: &[u8]) -> Self {
+        self.report_descriptor = report_descriptor.to_vec();
+        self
+    }
+
+    /// Set the response to [`HidDevice::get_feature_report`], the first byte of
+    /// `data` is the Report ID
+    ///
+    /// Feature reports sent by the application replace the response as well.
+    pub fn feature_report(self, data: &[u8]) -> Self {
+        if let Some(&report_id) = data.first() {
+            self.state()
+                .feature_reports
+                .insert(report_id, data.to_vec());
+        }
+        self
+    }
+
+    /// Queue an Input report, which is returned by the next read
+    pub fn queue_input_report(&self, data: &[u8]) {
+        self.state().input_reports.push_back(data.to_vec());
+        self.shared.input_available.notify_all();
+    }
+
+    /// Take all reports sent with [`HidDevice::write`] and
+    /// [`HidDevice::send_output_report`] since the last call
+    pub fn take_written_reports(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.state().written_reports)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+}
+
+pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+    supports_feature_reports: true,
+    supports_get_input_report: false,
+    supports_set_input_report: false,
+    supports_report_descriptor: true,
+    supports_read_canceller: false,
+    supports_pending_report_check: false,
+    supports_indexed_strings: false,
+    supports_hotplug: false,
+    supports_control_transfer: false,
+};
+
+/// Open the first of the devices matching the predicate
+pub(crate) fn open(
+    devices: &[MockDevice],
+    matches: impl Fn(&DeviceInfo) -> bool,
+) -> Option<HidDevice> {
+    let device = devices.iter().find(|device| matches(&device.info))?;
+    Some(HidDevice::from_backend(Box::new(MockHidDevice {
+        device: device.clone(),
+        blocking: Cell::new(true),
+    })))
+}
+
+/// Open the device at `path`, which like a missing device node is an error
+/// other than [`HidError::DeviceNotFound`]
+pub(crate) fn open_path(devices: &[MockDevice], path: &CStr) -> HidResult<HidDevice> {
+    open(devices, |info| info.path.as_c_str() == path).ok_or_else(|| HidError::HidApiError {
+        message: format!("no device with path {path:?}"),
+    })
+}
+
+struct MockHidDevice {
+    device: MockDevice,
+    blocking: Cell<bool>,
+}
+
+impl HidDeviceBackendBase for MockHidDevice {
+    #[cfg(hidapi)]
+    fn check_error(&self) -> HidResult<HidError> {
+        Ok(HidError::HidApiErrorEmpty)
+    }
+
+    fn write(&self, data: &[u8]) -> HidResult<usize> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+        self.device.state().written_reports.push(data.to_vec());
+        Ok(data.len())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
+        let timeout = if self.blocking.get() { -1 } else { 0 };
+        self.read_timeout(buf, timeout)
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        let shared = &self.device.shared;
+        let no_input = |state: &mut State| state.input_reports.is_empty();
+        let state = shared.state.lock().unwrap();
+
+        let mut state = match u64::try_from(timeout) {
+            Ok(ms) => {
+                shared
+                    .input_available
+                    .wait_timeout_while(state, Duration::from_millis(ms), no_input)
+                    .unwrap()
+                    .0
+            }
+            Err(_) => shared.input_available.wait_while(state, no_input).unwrap(),
+        };
+
+        match state.input_reports.pop_front() {
+            Some(report) => {
+                let len = report.len().min(buf.len());
+                buf[..len].copy_from_slice(&report[..len]);
+                Ok(len)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn queued_report_count(&self) -> HidResult<usize> {
+        Ok(self.device.state().input_reports.len())
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+        self.device
+            .state()
+            .feature_reports
+            .insert(data[0], data.to_vec());
+        Ok(())
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        let report_id = *buf.first().ok_or(HidError::InvalidZeroSizeData)?;
+        match self.device.state().feature_reports.get(&report_id) {
+            Some(report) => {
+                let len = report.len().min(buf.len());
+                buf[..len].copy_from_slice(&report[..len]);
+                Ok(len)
+            }
+            None => Err(HidError::HidApiError {
+                message: format!("no feature report with id {report_id}"),
+            }),
+        }
+    }
+
+    fn send_output_report(&self, data: &[u8]) -> HidResult<()> {
+        self.write(data).map(|_| ())
+    }
+
+    #[cfg(any(hidapi, target_os = "linux"))]
+    fn get_input_report(&self, _data: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::HidApiError {
+            message: "get_input_report: not supported".to_string(),
+        })
+    }
+
+    fn set_blocking_mode(&self, blocking: bool) -> HidResult<()> {
+        self.blocking.set(blocking);
+        Ok(())
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.blocking.get()
+    }
+
+    fn get_device_info(&self) -> HidResult<DeviceInfo> {
+        Ok(self.device.info.clone())
+    }
+
+    fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
+        Ok(self.device.info.manufacturer_string().map(String::from))
+    }
+
+    fn get_product_string(&self) -> HidResult<Option<String>> {
+        Ok(self.device.info.product_string().map(String::from))
+    }
+
+    fn get_serial_number_string(&self) -> HidResult<Option<String>> {
+        Ok(self.device.info.serial_number().map(String::from))
+    }
+
+    fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
+        let descriptor = &self.device.report_descriptor;
+        let len = descriptor
+            .len()
+            .min(buf.len())
+            .min(MAX_REPORT_DESCRIPTOR_SIZE);
+        buf[..len].copy_from_slice(&descriptor[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl crate::HidDeviceBackendWindows for MockHidDevice {
+    fn get_container_id(&self) -> HidResult<crate::windows::GUID> {
+        Err(HidError::HidApiError {
+            message: "get_container_id: not supported".to_string(),
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl crate::HidDeviceBackendMacos for MockHidDevice {
+    fn get_location_id(&self) -> HidResult<u32> {
+        Ok(0)
+    }
+
+    fn is_open_exclusive(&self) -> HidResult<bool> {
+        Ok(true)
+    }
+
+    fn get_custom_property(&self, _key: &str) -> HidResult<Option<String>> {
+        Ok(None)
+    }
+
+    fn get_custom_property_i32(&self, _key: &str) -> HidResult<Option<i32>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::HidApi;
+
+    #[test]
+    fn test_enumerate_and_open() {
+        let api = HidApi::new_mocked(vec![
+            MockDevice::new(0x046d, 0xc52b).serial_number("A"),
+            MockDevice::new(0x046d, 0xc52b).serial_number("B"),
+            MockDevice::new(0x1234, 0x5678),
+        ]);
+        assert_eq!(3, api.device_list().count());
+
+        let device = api.open_serial(0x046d, 0xc52b, "B").unwrap();
+        assert_eq!(Some("B".into()), device.get_serial_number_string().unwrap());
+
+        let info = api.device_list().nth(2).unwrap();
+        let device = info.open_device(&api).unwrap();
+        assert_eq!(0x1234, device.get_device_info().unwrap().vendor_id());
+
+        assert!(matches!(
+            api.open(0x1234, 0x0000),
+            Err(HidError::DeviceNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_report() {
+        let mock = MockDevice::new(0x046d, 0xc52b);
+        let api = HidApi::new_mocked(vec![mock.clone()]);
+        let device = api.open(0x046d, 0xc52b).unwrap();
+
+        device
+            .set_report(crate::ReportType::Output, 0x03, &[0x01, 0x02])
+            .unwrap();
+        assert_eq!(vec![vec![0x03, 0x01, 0x02]], mock.take_written_reports());
+
+        device.send_feature_report_id(0x04, &[0xaa]).unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(1, device.get_feature_report_id(0x04, &mut buf).unwrap());
+        assert_eq!(0xaa, buf[0]);
+
+        assert!(device
+            .set_report(crate::ReportType::Input, 0x01, &[0x00])
+            .is_err());
+    }
+
+    #[test]
+    fn test_reports() {
+        let mock = MockDevice::new(0x046d, 0xc52b).feature_report(&[0x02, 0xaa]);
+        let api = HidApi::new_mocked(vec![mock.clone()]);
+        let device = api.open(0x046d, 0xc52b).unwrap();
+
+        device.write(&[0x00, 0x01]).unwrap();
+        device.send_output_report(&[0x00, 0x02]).unwrap();
+        assert_eq!(
+            vec![vec![0x00, 0x01], vec![0x00, 0x02]],
+            mock.take_written_reports()
+        );
+
+        assert_eq!(2, device.write_raw(&[0x00, 0x03]).unwrap());
+        assert_eq!(vec![vec![0x00, 0x00, 0x03]], mock.take_written_reports());
+        assert!(matches!(
+            device.write_raw(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+
+        let mut buf = [0u8; 4];
+        assert_eq!(0, device.read_timeout(&mut buf, 10).unwrap());
+        mock.queue_input_report(&[0x01, 0x02, 0x03]);
+        assert_eq!(3, device.read(&mut buf).unwrap());
+        assert_eq!([0x01, 0x02, 0x03], buf[..3]);
+
+        let stats = device.stats();
+        assert_eq!(
+            (1, 3, 0),
+            (
+                stats.reports_received,
+                stats.bytes_received,
+                stats.read_errors
+            )
+        );
+        assert!(stats.last_report.is_some());
+
+        let mut buf = [0x02, 0x00];
+        assert_eq!(2, device.get_feature_report(&mut buf).unwrap());
+        assert_eq!([0x02, 0xaa], buf);
+        device.send_feature_report(&[0x02, 0xbb]).unwrap();
+        assert_eq!(2, device.get_feature_report(&mut buf).unwrap());
+        assert_eq!([0x02, 0xbb], buf);
+    }
+}
@@ -0,0 +1,88 @@
+//! Helpers to extract fields from the raw bytes of a report
+//!
+//! Fields in HID reports are not necessarily byte aligned, so all offsets are
+//! given in bits. Like in the report descriptor, bit 0 is the least significant
+//! bit of the first byte, and multi-byte fields are little-endian.
+//!
+//! ```
+//! use hidapi::report::{read_bits, read_i16_le};
+//!
+//! let report = [0x01, 0x9c, 0xff, 0b0000_0101];
+//! assert_eq!(Some(-100), read_i16_le(&report, 8));
+//! assert_eq!(Some(0b101), read_bits(&report, 24, 3));
+//! ```
+
+/// Read an unsigned field of `len` bits (at most 32) starting at `offset`
+///
+/// Returns `None` if the field does not fit into `buf`.
+pub fn read_bits(buf: &[u8], offset: usize, len: usize) -> Option<u32> {
+    if len == 0 || len > 32 || offset.checked_add(len)? > buf.len() * 8 {
+        return None;
+    }
+
+    let bytes = &buf[offset / 8..=(offset + len - 1) / 8];
+    let value = bytes
+        .iter()
+        .enumerate()
+        .fold(0u64, |value, (i, &b)| value | u64::from(b) << (8 * i));
+
+    Some(((value >> (offset % 8)) & ((1u64 << len) - 1)) as u32)
+}
+
+/// Read a two's complement field of `len` bits (at most 32) starting at `offset`
+pub fn read_signed_bits(buf: &[u8], offset: usize, len: usize) -> Option<i32> {
+    read_bits(buf, offset, len).map(|raw| sign_extend(raw, len))
+}
+
+/// Read a little-endian `u16` starting at the bit `offset`
+pub fn read_u16_le(buf: &[u8], offset: usize) -> Option<u16> {
+    read_bits(buf, offset, 16).map(|raw| raw as u16)
+}
+
+/// Read a little-endian `i16` starting at the bit `offset`
+pub fn read_i16_le(buf: &[u8], offset: usize) -> Option<i16> {
+    read_u16_le(buf, offset).map(|raw| raw as i16)
+}
+
+/// Read a big-endian `u16` starting at the bit `offset`
+///
+/// This is for vendor specific reports, fields declared in the report
+/// descriptor are always little-endian.
+pub fn read_u16_be(buf: &[u8], offset: usize) -> Option<u16> {
+    read_u16_le(buf, offset).map(u16::swap_bytes)
+}
+
+/// Read a big-endian `i16` starting at the bit `offset`
+pub fn read_i16_be(buf: &[u8], offset: usize) -> Option<i16> {
+    read_u16_be(buf, offset).map(|raw| raw as i16)
+}
+
+pub(crate) fn sign_extend(raw: u32, len: usize) -> i32 {
+    let shift = 32 - len;
+    ((raw << shift) as i32) >> shift
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_bits() {
+        assert_eq!(Some(0b101), read_bits(&[0b1010_0000], 5, 3));
+        assert_eq!(Some(0x3ff), read_bits(&[0xff, 0xff], 4, 10));
+        assert_eq!(None, read_bits(&[0xff], 4, 10));
+        assert_eq!(None, read_bits(&[0xff], usize::MAX, 1));
+        assert_eq!(Some(-1), read_signed_bits(&[0xff], 0, 8));
+        assert_eq!(-2048, sign_extend(0x800, 12));
+    }
+
+    #[test]
+    fn test_read_16() {
+        let buf = [0x00, 0x34, 0x12, 0xff];
+        assert_eq!(Some(0x1234), read_u16_le(&buf, 8));
+        assert_eq!(Some(0x3412), read_u16_be(&buf, 8));
+        assert_eq!(Some(-238), read_i16_le(&buf, 16));
+        assert_eq!(Some(0x1234 >> 4 | 0xf000), read_u16_le(&buf, 12));
+        assert_eq!(None, read_i16_be(&buf, 24));
+    }
+}
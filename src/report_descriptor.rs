@@ -0,0 +1,339 @@
+//! Parsing of raw HID report descriptors, shared by all backends
+
+use crate::{HidError, HidResult, ReportType};
+
+#[derive(Default)]
+pub struct ReportDescriptor(Vec<u8>);
+
+impl ReportDescriptor {
+    /// Create a descriptor from the raw bytes
+    pub fn from_vec(value: Vec<u8>) -> Self {
+        ReportDescriptor(value)
+    }
+
+    /// Create a descriptor from a slice
+    ///
+    /// It returns an error if the value slice is too large for it to be a HID
+    /// descriptor
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn from_slice(value: &[u8]) -> HidResult<Self> {
+        Ok(Self::from_vec(value.to_vec()))
+    }
+
+    /// Iterate over the usage page and usage of the collections, or of the whole
+    /// descriptor if it has no collections
+    pub fn usages(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        let mut items = self.items();
+        let mut usage_page = 0;
+        let mut usage = None;
+        let mut found = false;
+
+        std::iter::from_fn(move || {
+            for item in items.by_ref() {
+                match item.prefix {
+                    // Usage Page 6.2.2.7 (Global)
+                    0x04 => usage_page = item.data as u16,
+                    // Usage 6.2.2.8 (Local)
+                    0x08 => usage = Some(item.data as u16),
+                    // Collection 6.2.2.4 (Main), Usage is a Local Item, unset it
+                    0xa0 => {
+                        if let Some(usage) = usage.take() {
+                            found = true;
+                            return Some((usage_page, usage));
+                        }
+                    }
+                    // Input, Output, Feature and End Collection 6.2.2.4 (Main)
+                    0x80 | 0x90 | 0xb0 | 0xc0 => usage = None,
+                    _ => {}
+                }
+            }
+
+            // The usage of a descriptor without collections
+            if !found {
+                found = true;
+                return usage.take().map(|usage| (usage_page, usage));
+            }
+            None
+        })
+    }
+}
+
+/// A short item of a report descriptor, see 6.2.2.2 of the HID specification
+pub struct Item {
+    /// The tag and type bits of the item prefix, with the size bits masked out
+    pub prefix: u8,
+    pub data: u32,
+    #[cfg_attr(not(feature = "input"), allow(dead_code))]
+    pub size: usize,
+}
+
+#[cfg(feature = "input")]
+impl Item {
+    /// The data of the item, interpreted as a two's complement number
+    pub fn signed_data(&self) -> i32 {
+        match self.size {
+            1 => self.data as u8 as i8 as i32,
+            2 => self.data as u16 as i16 as i32,
+            _ => self.data as i32,
+        }
+    }
+}
+
+impl ReportDescriptor {
+    /// Get the length of the report data (without the Report ID) of the report
+    /// with the given type and ID, if the descriptor declares it
+    pub fn report_len(&self, report_type: ReportType, report_id: u8) -> Option<usize> {
+        let main_prefix = match report_type {
+            ReportType::Input => 0x80,
+            ReportType::Output => 0x90,
+            ReportType::Feature => 0xb0,
+        };
+        // Report Size, Report Count and Report ID, which are all Global items
+        let mut global = (0usize, 0usize, 0u8);
+        let mut global_stack = Vec::new();
+        let mut bits: Option<usize> = None;
+
+        for item in self.items() {
+            match item.prefix {
+                prefix if prefix == main_prefix && global.2 == report_id => {
+                    let item_bits = global.0.checked_mul(global.1)?;
+                    bits = Some(bits.unwrap_or(0).checked_add(item_bits)?);
+                }
+                0x74 => global.0 = item.data as usize,
+                0x94 => global.1 = item.data as usize,
+                0x84 => global.2 = item.data as u8,
+                0xa4 => global_stack.push(global),
+                0xb4 => global = global_stack.pop().unwrap_or_default(),
+                _ => {}
+            }
+        }
+
+        bits.map(|bits| bits.div_ceil(8))
+    }
+
+    /// Check if the descriptor declares Report IDs, in which case every report
+    /// starts with its Report ID
+    pub fn uses_numbered_reports(&self) -> bool {
+        self.items().any(|item| item.prefix == 0x84)
+    }
+
+    /// Get the length of the longest report of the given type, including the
+    /// Report ID if the descriptor uses numbered reports
+    pub fn max_report_len(&self, report_type: ReportType) -> Option<usize> {
+        let mut report_ids = vec![0u8];
+        for item in self.items().filter(|item| item.prefix == 0x84) {
+            if !report_ids.contains(&(item.data as u8)) {
+                report_ids.push(item.data as u8);
+            }
+        }
+
+        report_ids
+            .into_iter()
+            .filter_map(|id| {
+                let len = self.report_len(report_type, id)?;
+                Some(if id == 0 { len } else { len + 1 })
+            })
+            .max()
+    }
+
+    /// Check that the descriptor consists of complete items only
+    ///
+    /// Returns [`HidError::InvalidDescriptor`] with the offset of the first
+    /// truncated item otherwise. The other methods stop at such an item.
+    #[cfg_attr(not(any(test, feature = "input")), allow(dead_code))]
+    pub fn validate(&self) -> HidResult<()> {
+        let mut offset = 0;
+
+        while let Some(&key) = self.0.get(offset) {
+            let len = match key {
+                // Long Item 6.2.2.3, with the data size and the tag after the prefix
+                0xfe => match self.0.get(offset + 1) {
+                    Some(&size) => 3 + usize::from(size),
+                    None => return Err(HidError::InvalidDescriptor { offset }),
+                },
+                key if key & 0x03 == 3 => 5,
+                key => 1 + usize::from(key & 0x03),
+            };
+            if offset + len > self.0.len() {
+                return Err(HidError::InvalidDescriptor { offset });
+            }
+            offset += len;
+        }
+
+        Ok(())
+    }
+
+    /// Iterate over the short items of the descriptor, skipping long items
+    ///
+    /// Iteration stops at the first truncated item.
+    pub fn items(&self) -> impl Iterator<Item = Item> + '_ {
+        let mut data = &self.0[..];
+
+        std::iter::from_fn(move || loop {
+            let (&key, rest) = data.split_first()?;
+
+            // Long Item 6.2.2.3, the next byte contains the length of the data
+            if key == 0xfe {
+                let len = usize::from(*rest.first()?);
+                data = rest.get(len + 2..)?;
+                continue;
+            }
+
+            let size = match key & 0x03 {
+                3 => 4,
+                v => v.into(),
+            };
+            let bytes = rest.get(..size)?;
+            data = &rest[size..];
+
+            let mut value = [0u8; 4];
+            value[..size].copy_from_slice(bytes);
+            return Some(Item {
+                prefix: key & 0xfc,
+                data: u32::from_le_bytes(value),
+                size,
+            });
+        })
+    }
+}
+
+impl AsRef<[u8]> for ReportDescriptor {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// Deterministic pseudo random bytes (xorshift), to feed the parser garbage
+    pub(crate) fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// A descriptor with a reserved short item prefix, `0xf4`, which must not be
+    /// mistaken for a long item
+    #[rustfmt::skip]
+    pub(crate) const RESERVED_ITEM: &[u8] = &[
+        0xf4,             // Reserved, without data
+        0x05, 0x0c,       // Usage Page (Consumer)
+        0x09, 0x01,       // Usage (Consumer Control)
+        0xa1, 0x01,       // Collection (Application)
+        0x75, 0x08,       //   Report Size (8)
+        0x95, 0x02,       //   Report Count (2)
+        0x81, 0x02,       //   Input (Data, Variable, Absolute)
+        0xc0,             // End Collection
+    ];
+
+    #[test]
+    fn test_reserved_item() {
+        let desc = ReportDescriptor::from_slice(RESERVED_ITEM).unwrap();
+        assert!(desc.validate().is_ok());
+        let prefixes: Vec<_> = desc.items().map(|item| item.prefix).collect();
+        assert_eq!(
+            vec![0xf4, 0x04, 0x08, 0xa0, 0x74, 0x94, 0x80, 0xc0],
+            prefixes
+        );
+        assert_eq!(vec![(0x0c, 0x01)], desc.usages().collect::<Vec<_>>());
+        assert_eq!(Some(2), desc.report_len(ReportType::Input, 0));
+    }
+
+    #[test]
+    fn test_validate() {
+        let data = include_bytes!("../tests/assets/mouse1.data");
+        assert!(ReportDescriptor::from_slice(data)
+            .unwrap()
+            .validate()
+            .is_ok());
+
+        // Usage Page with a 2 byte value, but only one byte left
+        let desc = ReportDescriptor::from_vec(vec![0x05, 0x01, 0x06, 0x01]);
+        assert!(matches!(
+            desc.validate(),
+            Err(HidError::InvalidDescriptor { offset: 2 })
+        ));
+        // Long item with 4 data bytes, but only 2 left
+        let desc = ReportDescriptor::from_vec(vec![0xfe, 0x04, 0x10, 0x00, 0x00]);
+        assert!(matches!(
+            desc.validate(),
+            Err(HidError::InvalidDescriptor { offset: 0 })
+        ));
+        assert!(ReportDescriptor::from_vec(vec![0xfe]).validate().is_err());
+    }
+
+    #[test]
+    fn test_random_descriptors() {
+        // None of this may panic
+        for seed in 0..2000 {
+            let desc = ReportDescriptor::from_vec(random_bytes(seed, (seed % 300) as usize));
+            let _ = desc.validate();
+            let _ = desc.usages().count();
+            let _ = desc.items().count();
+            for report_type in [ReportType::Input, ReportType::Output, ReportType::Feature] {
+                let _ = desc.max_report_len(report_type);
+            }
+        }
+    }
+
+    #[test]
+    fn test_report_descriptor_1() {
+        let data = include_bytes!("../tests/assets/mouse1.data");
+        let desc = ReportDescriptor::from_slice(&data[..]).expect("descriptor");
+        let values = desc.usages().collect::<Vec<_>>();
+
+        assert_eq!(vec![(65468, 136)], values);
+    }
+
+    #[test]
+    fn test_report_len() {
+        #[rustfmt::skip]
+        let desc = ReportDescriptor::from_vec(vec![
+            0x85, 0x01,       // Report ID (1)
+            0x75, 0x08,       // Report Size (8)
+            0x95, 0x03,       // Report Count (3)
+            0x81, 0x02,       // Input (Data, Variable, Absolute)
+            0xb1, 0x02,       // Feature (Data, Variable, Absolute)
+            0x85, 0x02,       // Report ID (2)
+            0x75, 0x01,       // Report Size (1)
+            0x95, 0x0c,       // Report Count (12)
+            0xb1, 0x02,       // Feature (Data, Variable, Absolute)
+            0x95, 0x04,       // Report Count (4)
+            0xb1, 0x03,       // Feature (Constant), padding
+        ]);
+
+        assert_eq!(Some(3), desc.report_len(ReportType::Input, 1));
+        assert_eq!(Some(3), desc.report_len(ReportType::Feature, 1));
+        assert_eq!(Some(2), desc.report_len(ReportType::Feature, 2));
+        assert_eq!(None, desc.report_len(ReportType::Output, 1));
+        assert_eq!(None, desc.report_len(ReportType::Input, 2));
+
+        assert_eq!(Some(4), desc.max_report_len(ReportType::Feature));
+        assert_eq!(Some(4), desc.max_report_len(ReportType::Input));
+        assert_eq!(None, desc.max_report_len(ReportType::Output));
+        assert!(desc.uses_numbered_reports());
+
+        // Unnumbered reports have no Report ID byte
+        let desc = ReportDescriptor::from_vec(vec![0x75, 0x08, 0x95, 0x40, 0x81, 0x02]);
+        assert_eq!(Some(64), desc.max_report_len(ReportType::Input));
+        assert!(!desc.uses_numbered_reports());
+    }
+
+    #[test]
+    fn test_report_descriptor_2() {
+        let data = include_bytes!("../tests/assets/mouse2.data");
+        let desc = ReportDescriptor::from_slice(&data[..]).expect("descriptor");
+        let values = desc.usages().collect::<Vec<_>>();
+
+        let expected = vec![(1, 2), (1, 1), (1, 128), (12, 1), (65280, 14)];
+        assert_eq!(expected, values);
+    }
+}
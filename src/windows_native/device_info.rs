@@ -57,10 +57,12 @@ pub fn get_device_info(path: &U16Str, handle: &Handle) -> DeviceInfo {
         release_number: attrib.VersionNumber,
         manufacturer_string: read_string(HidD_GetManufacturerString, handle),
         product_string: read_string(HidD_GetProductString, handle),
+        raw_name: WcharString::None,
         usage_page: caps.UsagePage,
         usage: caps.Usage,
         interface_number: -1,
         bus_type: BusType::Unknown,
+        country_code: None,
     };
 
     // If this fails just ignore it. The data might be incomplete but at least there is something
@@ -23,6 +23,7 @@ use std::ptr::{null, null_mut};
 use std::{
     ffi::CStr,
     fmt::{self, Debug},
+    time::Instant,
 };
 
 use crate::windows_native::dev_node::DevNode;
@@ -32,15 +33,21 @@ use crate::windows_native::hid::{get_hid_attributes, PreparsedData};
 use crate::windows_native::interfaces::Interface;
 use crate::windows_native::string::{U16Str, U16String};
 use crate::windows_native::types::{Handle, Overlapped};
-use crate::{DeviceInfo, HidDeviceBackendBase, HidDeviceBackendWindows, HidError, HidResult};
+use crate::{
+    check_deadline, Capabilities, DeviceInfo, HidDeviceBackendBase, HidDeviceBackendWindows,
+    HidError, HidResult,
+};
 use windows_sys::core::GUID;
 use windows_sys::Win32::Devices::HumanInterfaceDevice::{
-    HidD_GetIndexedString, HidD_SetFeature, HidD_SetNumInputBuffers, HidD_SetOutputReport,
+    HidD_GetFeature, HidD_GetIndexedString, HidD_SetFeature, HidD_SetNumInputBuffers,
+    HidD_SetOutputReport,
 };
 use windows_sys::Win32::Devices::Properties::{
     DEVPKEY_Device_ContainerId, DEVPKEY_Device_InstanceId,
 };
-use windows_sys::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE, INVALID_HANDLE_VALUE, TRUE};
+use windows_sys::Win32::Foundation::{
+    ERROR_INVALID_FUNCTION, GENERIC_READ, GENERIC_WRITE, INVALID_HANDLE_VALUE, TRUE,
+};
 use windows_sys::Win32::Storage::FileSystem::{
     CreateFileW, ReadFile, WriteFile, FILE_FLAG_OVERLAPPED, FILE_SHARE_READ, FILE_SHARE_WRITE,
     OPEN_EXISTING,
@@ -52,16 +59,40 @@ const STRING_BUF_LEN: usize = 128;
 
 pub struct HidApiBackend;
 impl HidApiBackend {
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        supports_feature_reports: true,
+        supports_get_input_report: false,
+        supports_set_input_report: false,
+        supports_report_descriptor: true,
+        supports_read_canceller: false,
+        supports_pending_report_check: false,
+        supports_indexed_strings: true,
+        supports_hotplug: false,
+        supports_control_transfer: false,
+    };
+
+    /// Passed to HidD_SetNumInputBuffers() when opening a device
+    pub const INPUT_QUEUE_SIZE: usize = 64;
+
     pub fn get_hid_device_info_vector(vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
-        Ok(enumerate_devices(vid, pid)?)
+        enumerate_devices(vid, pid, None)
     }
 
     pub fn open(vid: u16, pid: u16) -> HidResult<HidDevice> {
-        open(vid, pid, None)
+        open(vid, pid, None, None)
     }
 
     pub fn open_serial(vid: u16, pid: u16, sn: &str) -> HidResult<HidDevice> {
-        open(vid, pid, Some(sn))
+        open(vid, pid, Some(sn), None)
+    }
+
+    pub fn open_deadline(
+        vid: u16,
+        pid: u16,
+        sn: Option<&str>,
+        deadline: Option<Instant>,
+    ) -> HidResult<HidDevice> {
+        open(vid, pid, sn, deadline)
     }
 
     pub fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
@@ -264,6 +295,11 @@ impl HidDeviceBackendBase for HidDevice {
         };
         if res != TRUE {
             let err = Win32Error::last();
+            // Some drivers reject the ioctl with "Incorrect function", but still
+            // serve HidD_GetFeature()
+            if err == Win32Error::Generic(ERROR_INVALID_FUNCTION) {
+                return get_feature_report_sync(&self.device_handle, &mut state, buf);
+            }
             ensure!(err == Win32Error::IoPending, Err(err.into()))
         }
 
@@ -297,6 +333,10 @@ impl HidDeviceBackendBase for HidDevice {
         Ok(())
     }
 
+    fn is_blocking(&self) -> bool {
+        self.blocking.get()
+    }
+
     fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
         Ok(self.device_info.manufacturer_string().map(String::from))
     }
@@ -365,17 +405,26 @@ impl Drop for HidDevice {
     }
 }
 
-fn enumerate_devices(vendor_id: u16, product_id: u16) -> WinResult<Vec<DeviceInfo>> {
-    Ok(Interface::get_interface_list()?
-        .iter()
-        .filter_map(|device_interface| {
-            let device_handle = open_device(device_interface, false).ok()?;
-            let attrib = get_hid_attributes(&device_handle);
-            ((vendor_id == 0 || attrib.VendorID == vendor_id)
-                && (product_id == 0 || attrib.ProductID == product_id))
-                .then(|| get_device_info(device_interface, &device_handle))
-        })
-        .collect())
+/// Enumerate the HID interfaces, giving up once `deadline` has passed
+fn enumerate_devices(
+    vendor_id: u16,
+    product_id: u16,
+    deadline: Option<Instant>,
+) -> HidResult<Vec<DeviceInfo>> {
+    let mut devices = Vec::new();
+    for device_interface in Interface::get_interface_list()?.iter() {
+        check_deadline(deadline)?;
+        let Ok(device_handle) = open_device(device_interface, false) else {
+            continue;
+        };
+        let attrib = get_hid_attributes(&device_handle);
+        if (vendor_id == 0 || attrib.VendorID == vendor_id)
+            && (product_id == 0 || attrib.ProductID == product_id)
+        {
+            devices.push(get_device_info(device_interface, &device_handle));
+        }
+    }
+    Ok(devices)
 }
 
 fn open_device(path: &U16Str, open_rw: bool) -> WinResult<Handle> {
@@ -400,15 +449,46 @@ fn open_device(path: &U16Str, open_rw: bool) -> WinResult<Handle> {
     Ok(Handle::from_raw(handle))
 }
 
-fn open(vid: u16, pid: u16, sn: Option<&str>) -> HidResult<HidDevice> {
-    let dev = enumerate_devices(vid, pid)?
+fn open(vid: u16, pid: u16, sn: Option<&str>, deadline: Option<Instant>) -> HidResult<HidDevice> {
+    let dev = enumerate_devices(vid, pid, deadline)?
         .into_iter()
         .filter(|dev| dev.vendor_id == vid && dev.product_id == pid)
         .find(|dev| sn.map_or(true, |sn| dev.serial_number().is_some_and(|n| sn == n)))
-        .ok_or(HidError::HidApiErrorEmpty)?;
+        .ok_or_else(|| HidError::device_not_found(vid, pid, sn))?;
+    check_deadline(deadline)?;
     open_path(dev.path())
 }
 
+/// Read a Feature report with HidD_GetFeature(), for drivers which do not support
+/// IOCTL_HID_GET_FEATURE
+fn get_feature_report_sync(
+    handle: &Handle,
+    state: &mut AsyncState,
+    buf: &mut [u8],
+) -> HidResult<usize> {
+    // The buffer must be as long as the longest Feature report, like for writes
+    state.fill_buffer(&buf[..1]);
+    if unsafe {
+        HidD_GetFeature(
+            handle.as_raw(),
+            state.buffer_ptr() as _,
+            state.buffer_len() as u32,
+        )
+    } == 0
+    {
+        return Err(HidError::HidApiError {
+            message: format!(
+                "get_feature_report: the driver does not support GET_REPORT requests ({:?})",
+                Win32Error::last()
+            ),
+        });
+    }
+
+    let len = buf.len().min(state.buffer_len());
+    buf[..len].copy_from_slice(&state.buffer[..len]);
+    Ok(len)
+}
+
 fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
     let device_path = U16String::try_from(device_path).unwrap();
     let handle = open_device(&device_path, true)
@@ -418,7 +498,9 @@ fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
         // can still be sent and received.  Retry opening the device, but
         // without read/write access.
         .or_else(|_| open_device(&device_path, false))?;
-    check_boolean(unsafe { HidD_SetNumInputBuffers(handle.as_raw(), 64) })?;
+    check_boolean(unsafe {
+        HidD_SetNumInputBuffers(handle.as_raw(), HidApiBackend::INPUT_QUEUE_SIZE as u32)
+    })?;
     let caps = PreparsedData::load(&handle)?.get_caps()?;
     let device_info = get_device_info(&device_path, &handle);
     let dev = HidDevice {